@@ -0,0 +1,113 @@
+use super::{Air, BoundaryConstraint};
+use crate::common::finite_fields::MyField;
+
+// Public inputs of a FibonacciSq statement: the sequence starting with first_element and second_element reaches
+// claimed_output after num_steps elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FibonacciSqPublicInputs {
+    pub first_element: MyField,
+    pub second_element: MyField,
+    pub claimed_output: MyField,
+    pub num_steps: usize,
+}
+
+// The statement of the STARK 101 tutorial: a[0] = 1, a[1] = 3141592 and a[1022] = 2338775057
+impl Default for FibonacciSqPublicInputs {
+    fn default() -> Self {
+        Self {
+            first_element: MyField::from(1),
+            second_element: MyField::from(3141592),
+            claimed_output: MyField::from(2338775057_u64),
+            num_steps: 1023,
+        }
+    }
+}
+
+// FibonacciSq program: a[i + 2] = a[i + 1]^2 + a[i]^2
+// The statement proven is that the sequence starting with the first and second elements of the public inputs reaches
+// the claimed output at step num_steps - 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FibonacciSq {
+    pub public_inputs: FibonacciSqPublicInputs,
+}
+
+impl FibonacciSq {
+    pub fn new(public_inputs: FibonacciSqPublicInputs) -> Self {
+        Self::try_new(public_inputs).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Same as new, but returns the reason for which the public inputs are invalid instead of panicking
+    pub fn try_new(public_inputs: FibonacciSqPublicInputs) -> Result<Self, String> {
+        if public_inputs.num_steps < 3 {
+            return Err("FibonacciSq must run for at least 3 steps".to_string());
+        }
+        Ok(Self { public_inputs })
+    }
+
+    // Generates the execution trace: a single column holding the first num_steps elements of the sequence
+    pub fn generate_trace(&self) -> Vec<Vec<MyField>> {
+        let mut a = vec![
+            self.public_inputs.first_element,
+            self.public_inputs.second_element,
+        ];
+        for i in 2..self.trace_length() {
+            a.push(a[i - 2] * a[i - 2] + a[i - 1] * a[i - 1]);
+        }
+        vec![a]
+    }
+}
+
+impl Air for FibonacciSq {
+    fn trace_width(&self) -> usize {
+        1
+    }
+
+    fn trace_length(&self) -> usize {
+        self.public_inputs.num_steps
+    }
+
+    fn public_inputs(&self) -> Vec<MyField> {
+        vec![
+            self.public_inputs.first_element,
+            self.public_inputs.second_element,
+            self.public_inputs.claimed_output,
+            MyField::from(self.public_inputs.num_steps as u64),
+        ]
+    }
+
+    // a[0] = first_element, a[1] = second_element and a[num_steps - 1] = claimed_output
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint> {
+        vec![
+            BoundaryConstraint {
+                column: 0,
+                step: 0,
+                value: self.public_inputs.first_element,
+            },
+            BoundaryConstraint {
+                column: 0,
+                step: 1,
+                value: self.public_inputs.second_element,
+            },
+            BoundaryConstraint {
+                column: 0,
+                step: self.trace_length() - 1,
+                value: self.public_inputs.claimed_output,
+            },
+        ]
+    }
+
+    // The transition constraint reads a[i], a[i + 1] and a[i + 2]
+    fn transition_frame_size(&self) -> usize {
+        3
+    }
+
+    fn transition_constraint_degrees(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    // a[i + 2] - a[i + 1]^2 - a[i]^2
+    fn evaluate_transition(&self, frame: &[Vec<MyField>]) -> Vec<MyField> {
+        let (a0, a1, a2) = (frame[0][0], frame[1][0], frame[2][0]);
+        vec![a2 - a1 * a1 - a0 * a0]
+    }
+}
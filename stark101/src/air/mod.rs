@@ -0,0 +1,216 @@
+pub mod fibonacci_sq;
+
+use crate::common::{
+    finite_fields::{MyField, MyFieldExt},
+    polynomials::missing_points_vanishing_values,
+};
+use ark_ff::{batch_inversion, AdditiveGroup, FftField, Field};
+use rayon::prelude::*;
+use std::iter::zip;
+
+// A boundary constraint asserts that the cell of a column at a given step equals a public value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundaryConstraint {
+    pub column: usize,
+    pub step: usize,
+    pub value: MyField,
+}
+
+// Algebraic Intermediate Representation (AIR) of a computation
+// The execution trace is a table of trace_width() columns and trace_length() rows (steps). It is valid when every
+// boundary constraint holds and the transition constraints vanish on every frame of consecutive rows of the trace.
+pub trait Air: Send + Sync {
+    // Number of columns of the execution trace
+    fn trace_width(&self) -> usize;
+
+    // Number of rows (steps) of the execution trace
+    fn trace_length(&self) -> usize;
+
+    // Public inputs of the statement being proven, absorbed into the channel before any commitment
+    fn public_inputs(&self) -> Vec<MyField>;
+
+    // Constraints binding cells of the trace to public values
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint>;
+
+    // Number of consecutive rows read by the transition constraints (2 for current and next rows)
+    fn transition_frame_size(&self) -> usize;
+
+    // Degrees of the transition constraints as polynomials in the cells of the frame
+    fn transition_constraint_degrees(&self) -> Vec<usize>;
+
+    // Evaluates the transition constraints on a frame of consecutive rows, where frame[k][column] is the cell of the
+    // column at step i + k. All evaluations must be zero for a valid trace.
+    fn evaluate_transition(&self, frame: &[Vec<MyField>]) -> Vec<MyField>;
+
+    // Total number of constraints, each of them gets its own random coefficient in the composition polynomial
+    fn num_constraints(&self) -> usize {
+        self.boundary_constraints().len() + self.transition_constraint_degrees().len()
+    }
+
+    // Number of steps i at which the transition constraints are enforced (frames fitting in the trace)
+    fn transition_steps(&self) -> usize {
+        self.trace_length() + 1 - self.transition_frame_size()
+    }
+
+    // Size of the group over which the trace columns are interpolated
+    fn trace_domain_size(&self) -> usize {
+        self.trace_length().next_power_of_two()
+    }
+
+    // Generator of the trace domain
+    fn trace_generator(&self) -> MyField {
+        MyField::GENERATOR.pow([3221225472_u64 / self.trace_domain_size() as u64])
+    }
+
+    // Power of two strictly bounding the degree of the composition polynomial
+    // Trace polynomials have degree trace_length - 1, so that a transition constraint of degree d divided by its
+    // zerofier has degree d * (trace_length - 1) - transition_steps, and a boundary quotient has degree trace_length - 2.
+    fn composition_degree_bound(&self) -> usize {
+        let trace_degree = self.trace_length() - 1;
+        let transition_degree = self
+            .transition_constraint_degrees()
+            .iter()
+            .map(|degree| (degree * trace_degree).saturating_sub(self.transition_steps()))
+            .max()
+            .unwrap_or(0);
+        (transition_degree.max(trace_degree - 1) + 1).next_power_of_two()
+    }
+
+    // Returns the frame of consecutive rows starting at a step of a column-major trace
+    fn get_frame(&self, trace: &[Vec<MyField>], step: usize) -> Vec<Vec<MyField>> {
+        (0..self.transition_frame_size())
+            .map(|k| trace.iter().map(|column| column[step + k]).collect())
+            .collect()
+    }
+
+    // Checks that a column-major trace satisfies all boundary and transition constraints
+    fn is_valid_trace(&self, trace: &[Vec<MyField>]) -> bool {
+        trace.len() == self.trace_width()
+            && trace
+                .iter()
+                .all(|column| column.len() == self.trace_length())
+            && self
+                .boundary_constraints()
+                .iter()
+                .all(|constraint| trace[constraint.column][constraint.step] == constraint.value)
+            && (0..self.transition_steps()).all(|step| {
+                self.evaluate_transition(&self.get_frame(trace, step))
+                    .iter()
+                    .all(|evaluation| *evaluation == MyField::ZERO)
+            })
+    }
+
+    // Inverses of the divisors of the constraints on the points of a coset x, xh, xh^2, .. disjoint from the trace
+    // domain, h being of order a multiple of the size n of the trace domain
+    // The points xh^j, xh^j * g, xh^j * g^2, .. are found size / n positions apart: on each of these cosets of the trace
+    // domain x^n - 1 is constant, and the denominator of the transition zerofier is evaluated in O(n) from prefix
+    // products. All the divisors are then inverted in batches.
+    fn composition_divisors_on_coset(&self, eval_domain: &[MyField]) -> CompositionDivisors {
+        let g = self.trace_generator();
+        let boundary_constraints = self.boundary_constraints();
+        let boundary_inverses = boundary_constraints
+            .iter()
+            .map(|constraint| {
+                let g_step = g.pow([constraint.step as u64]);
+                let mut inverses: Vec<MyField> =
+                    eval_domain.par_iter().map(|x| *x - g_step).collect();
+                batch_inversion(&mut inverses);
+                inverses
+            })
+            .collect();
+        let n = self.trace_domain_size();
+        let num_cosets = eval_domain.len() / n;
+        let mut transition_inverses = vec![MyField::ZERO; eval_domain.len()];
+        transition_inverses
+            .par_chunks_mut(n)
+            .zip(&eval_domain[..num_cosets])
+            .for_each(|(chunk, y)| {
+                let vanishing_inverse = (y.pow([n as u64]) - MyField::ONE).inverse().unwrap();
+                for (value, denom) in zip(
+                    chunk,
+                    missing_points_vanishing_values(*y, self.transition_steps(), n, n),
+                ) {
+                    *value = denom * vanishing_inverse;
+                }
+            });
+        // Transpose the values grouped by coset of the trace domain back to the order of the points
+        let transition_inverses = (0..eval_domain.len())
+            .into_par_iter()
+            .map(|i| transition_inverses[(i % num_cosets) * n + i / num_cosets])
+            .collect();
+        CompositionDivisors {
+            boundary_constraints,
+            boundary_inverses,
+            transition_inverses,
+        }
+    }
+
+    // Inverses of the divisors of the constraints at a single point x outside of the trace domain
+    fn composition_divisors_at(&self, x: MyField) -> CompositionDivisors {
+        let g = self.trace_generator();
+        let boundary_constraints = self.boundary_constraints();
+        let boundary_inverses = boundary_constraints
+            .iter()
+            .map(|constraint| vec![(x - g.pow([constraint.step as u64])).inverse().unwrap()])
+            .collect();
+        let n = self.trace_domain_size();
+        let denom = missing_points_vanishing_values(x, self.transition_steps(), n, 1)[0];
+        let transition_inverses = vec![denom / (x.pow([n as u64]) - MyField::ONE)];
+        CompositionDivisors {
+            boundary_constraints,
+            boundary_inverses,
+            transition_inverses,
+        }
+    }
+
+    // Evaluates the composition polynomial at the i-th point x of the divisors, given the frame of the trace polynomials
+    // evaluated at x, gx, g^2x, ..
+    // Each constraint is divided by the polynomial vanishing where it must hold, and the quotients are combined with
+    // the random coefficients alphas of the extension field (boundary constraints first, then transition constraints).
+    fn evaluate_composition_with(
+        &self,
+        i: usize,
+        frame: &[Vec<MyField>],
+        divisors: &CompositionDivisors,
+        alphas: &[MyFieldExt],
+    ) -> MyFieldExt {
+        let mut result = MyFieldExt::ZERO;
+        for ((constraint, inverses), alpha) in zip(
+            zip(&divisors.boundary_constraints, &divisors.boundary_inverses),
+            alphas,
+        ) {
+            let numer = frame[0][constraint.column] - constraint.value;
+            result += *alpha * MyFieldExt::from_base_prime_field(numer * inverses[i]);
+        }
+        for (numer, alpha) in zip(
+            self.evaluate_transition(frame),
+            &alphas[divisors.boundary_constraints.len()..],
+        ) {
+            result +=
+                *alpha * MyFieldExt::from_base_prime_field(numer * divisors.transition_inverses[i]);
+        }
+        result
+    }
+
+    // Evaluates the composition polynomial at a point x outside of the trace domain, given the frame of the trace
+    // polynomials evaluated at x, gx, g^2x, ..
+    fn evaluate_composition(
+        &self,
+        x: MyField,
+        frame: &[Vec<MyField>],
+        alphas: &[MyFieldExt],
+    ) -> MyFieldExt {
+        self.evaluate_composition_with(0, frame, &self.composition_divisors_at(x), alphas)
+    }
+}
+
+// Inverses of the polynomials dividing the constraints of an AIR, evaluated on points outside of the trace domain
+// boundary_inverses[c][i] is 1 / (x_i - g^step) for the c-th boundary constraint, and transition_inverses[i] is the
+// inverse of the transition zerofier (x_i^n - 1) / ((x_i - g^transition_steps) * .. * (x_i - g^(n-1))), n being the
+// size of the trace domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositionDivisors {
+    pub boundary_constraints: Vec<BoundaryConstraint>,
+    pub boundary_inverses: Vec<Vec<MyField>>,
+    pub transition_inverses: Vec<MyField>,
+}
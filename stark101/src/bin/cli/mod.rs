@@ -0,0 +1,137 @@
+// Command-line arguments shared by the prover and verifier binaries
+use clap::{ArgAction, Args, Subcommand};
+use log::LevelFilter;
+use stark101::air::fibonacci_sq::FibonacciSqPublicInputs;
+use stark101::common::{
+    finite_fields::MyField,
+    options::{HashFunction, ProofOptions},
+    proof::ProofFormat,
+};
+use std::io::Write;
+
+#[derive(Args)]
+pub struct CommonArgs {
+    /// Path of the proof file, - for stdin/stdout
+    #[arg(long, global = true, default_value = "proofs/proof.json")]
+    pub proof: String,
+
+    /// Encoding of the proof file: json or binary
+    #[arg(long, global = true, default_value = "json")]
+    pub format: ProofFormat,
+
+    #[command(flatten)]
+    pub options: OptionsArgs,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Log the steps of the protocol (-vv for more details)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
+
+#[derive(Args)]
+pub struct OptionsArgs {
+    /// Ratio between the size of the evaluation domain and the degree bound of the committed polynomials
+    #[arg(long, global = true, default_value_t = 8)]
+    pub blowup: usize,
+
+    /// Number of queries decommitted at the end of the protocol
+    #[arg(long, global = true, default_value_t = 3)]
+    pub num_queries: usize,
+
+    /// Ratio between the sizes of consecutive FRI layers
+    #[arg(long, global = true, default_value_t = 2)]
+    pub folding_factor: usize,
+
+    /// Number of leading zero bits of the proof-of-work
+    #[arg(long, global = true, default_value_t = 0)]
+    pub grinding_bits: u32,
+
+    /// Hash function of the commitments and of the Fiat-Shamir transform: sha256, blake3, keccak256 or poseidon
+    #[arg(long, global = true, default_value = "sha256")]
+    pub hash: HashFunction,
+
+    /// Depth of the Merkle tree nodes committed in place of the roots
+    #[arg(long, global = true, default_value_t = 0)]
+    pub cap_height: usize,
+
+    /// Degree at which FRI stops folding and sends the remainder polynomial, a power of two minus one
+    #[arg(long, global = true, default_value_t = 0)]
+    pub max_remainder_degree: usize,
+}
+
+impl OptionsArgs {
+    pub fn proof_options(&self) -> Result<ProofOptions, String> {
+        ProofOptions::try_new(
+            self.blowup,
+            self.num_queries,
+            self.folding_factor,
+            self.grinding_bits,
+            self.hash,
+            self.cap_height,
+            self.max_remainder_degree,
+        )
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Statement {
+    /// a[i + 2] = a[i + 1]^2 + a[i]^2 reaches the claimed output after num_steps elements
+    FibonacciSq(FibonacciSqArgs),
+}
+
+impl Default for Statement {
+    fn default() -> Self {
+        Statement::FibonacciSq(FibonacciSqArgs {
+            first_element: 1,
+            second_element: 3141592,
+            num_steps: 1023,
+            claimed_output: None,
+        })
+    }
+}
+
+#[derive(Args)]
+pub struct FibonacciSqArgs {
+    #[arg(long, default_value_t = 1)]
+    pub first_element: u64,
+
+    #[arg(long, default_value_t = 3141592)]
+    pub second_element: u64,
+
+    #[arg(long, default_value_t = 1023)]
+    pub num_steps: usize,
+
+    /// Value of the last element (computed by the prover when omitted, 2338775057 for the verifier)
+    #[arg(long)]
+    pub claimed_output: Option<u64>,
+}
+
+impl FibonacciSqArgs {
+    pub fn public_inputs(&self, default_claimed_output: MyField) -> FibonacciSqPublicInputs {
+        FibonacciSqPublicInputs {
+            first_element: MyField::from(self.first_element),
+            second_element: MyField::from(self.second_element),
+            claimed_output: self
+                .claimed_output
+                .map_or(default_claimed_output, MyField::from),
+            num_steps: self.num_steps,
+        }
+    }
+}
+
+// Logs go to stderr so that the proof can be piped through stdout
+pub fn init_logger(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .init();
+}
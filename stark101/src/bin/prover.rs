@@ -1,17 +1,77 @@
-use serde_json;
+mod cli;
+
+use clap::Parser;
+use cli::{init_logger, CommonArgs, Statement};
+use log::{error, info};
+use stark101::air::{fibonacci_sq::FibonacciSq, Air};
+use stark101::common::proof::serialize_proof;
 use stark101::prover;
-use std::fs::File;
-use std::io::Write;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+// Generates a proof of a statement and writes it to a file or to stdout
+#[derive(Parser)]
+#[command(about = "Generates a STARK proof of a statement")]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(subcommand)]
+    statement: Option<Statement>,
+}
 
-fn main() {
-    let proof = prover::run();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_logger(cli.common.quiet, cli.common.verbose);
+    let options = match cli.common.options.proof_options() {
+        Ok(options) => options,
+        Err(err) => {
+            error!("Invalid options: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    // Convert to pretty JSON
-    let json = serde_json::to_string_pretty(&proof).expect("Failed to serialize proof");
+    let proof = match cli.statement.unwrap_or_default() {
+        Statement::FibonacciSq(args) => {
+            let air = match FibonacciSq::try_new(args.public_inputs(Default::default())) {
+                Ok(air) => air,
+                Err(err) => {
+                    error!("Invalid statement: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if let Err(err) = options.check(&air) {
+                error!("Invalid options: {}", err);
+                return ExitCode::FAILURE;
+            }
+            let trace = air.generate_trace();
+            let claimed_output = trace[0][args.num_steps - 1];
+            let air = FibonacciSq::new(args.public_inputs(claimed_output));
+            if !air.is_valid_trace(&trace) {
+                error!("The sequence does not reach the claimed output");
+                return ExitCode::FAILURE;
+            }
+            info!("Claimed output: {}", air.public_inputs.claimed_output);
+            prover::run(&air, &trace, &options)
+        }
+    };
 
-    // Save to file
-    let mut file = File::create("proofs/proof.json").expect("Failed to create file");
-    file.write_all(json.as_bytes())
-        .expect("Failed to write to file");
-    println!("✅ Proof saved successfully");
+    let bytes = serialize_proof(&proof, cli.common.format);
+    let result = match cli.common.proof.as_str() {
+        "-" => io::stdout()
+            .write_all(&bytes)
+            .and_then(|_| io::stdout().flush()),
+        path => fs::write(path, bytes),
+    };
+    match result {
+        Ok(()) => {
+            info!("✅ Proof saved successfully");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Failed to write proof: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
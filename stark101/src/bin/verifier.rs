@@ -1,21 +1,82 @@
-use serde_json;
-use stark101::common::channel::Member;
+mod cli;
+
+use clap::Parser;
+use cli::{init_logger, CommonArgs, Statement};
+use log::{error, info};
+use stark101::air::fibonacci_sq::{FibonacciSq, FibonacciSqPublicInputs};
+use stark101::common::proof::deserialize_proof;
 use stark101::verifier;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+// Reads a proof from a file or from stdin and verifies it against a statement
+// The exit code is non-zero when the proof is rejected.
+#[derive(Parser)]
+#[command(about = "Verifies a STARK proof of a statement")]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(subcommand)]
+    statement: Option<Statement>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_logger(cli.common.quiet, cli.common.verbose);
+    let options = match cli.common.options.proof_options() {
+        Ok(options) => options,
+        Err(err) => {
+            error!("Invalid options: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
-fn main() {
-    let mut file = File::open("proofs/proof.json").expect("Failed to open file");
-    let mut json_str = String::new();
-    file.read_to_string(&mut json_str)
-        .expect("Failed to read file");
+    let air = match cli.statement.unwrap_or_default() {
+        Statement::FibonacciSq(args) => {
+            let default_claimed_output = FibonacciSqPublicInputs::default().claimed_output;
+            match FibonacciSq::try_new(args.public_inputs(default_claimed_output)) {
+                Ok(air) => air,
+                Err(err) => {
+                    error!("Invalid statement: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+    if let Err(err) = options.check(&air) {
+        error!("Invalid options: {}", err);
+        return ExitCode::FAILURE;
+    }
 
-    let proof: Vec<Member> = serde_json::from_str(&json_str).expect("Failed to parse JSON");
-    println!("✅ Proof loaded successfully");
+    let bytes = match cli.common.proof.as_str() {
+        "-" => {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+        }
+        path => fs::read(path),
+    };
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            error!("Failed to read proof: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let result = verifier::run(proof);
+    let result = deserialize_proof(&bytes, cli.common.format).and_then(|proof| {
+        info!("✅ Proof loaded successfully");
+        verifier::verify(&air, &proof, &options)
+    });
     match result {
-        true => println!("✅ Proof verified successfully"),
-        false => println!("Proof Verification failed"),
+        Ok(()) => {
+            info!("✅ Proof verified successfully");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Proof Verification failed: {}", err);
+            ExitCode::FAILURE
+        }
     }
 }
@@ -1,159 +1,226 @@
-use crate::common::{finite_fields::MyField, utils::concat_slices};
-use ark_ff::{BigInteger, PrimeField};
-use hex::{decode, encode};
-use num_bigint::BigUint;
-use num_traits::cast::ToPrimitive;
-use rs_merkle::{algorithms::Sha256, Hasher};
-use serde::{Deserialize, Serialize};
-use std::fmt;
-
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Type {
-    Send,
-    Receive,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct Member {
-    pub member_type: Type,
-    #[serde(serialize_with = "serialize_hex_array")]
-    #[serde(deserialize_with = "deserialize_hex_array")]
-    pub data: Vec<u8>,
+use crate::common::{
+    error::VerifierError,
+    finite_fields::{MyField, MyFieldExt},
+    hasher::{Hasher, Sha256},
+};
+use ark_ff::{Field, PrimeField};
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+
+// Prefixes of the data hashed with the state when sending a message, squeezing a block of random bytes and checking a
+// proof-of-work, so that no hash computed for one of these uses can be passed off as a hash computed for another one
+pub const SEND_PREFIX: u8 = 0x00;
+pub const SQUEEZE_PREFIX: u8 = 0x01;
+pub const PROOF_OF_WORK_PREFIX: u8 = 0x02;
+
+// Number of nonces searched in parallel at once when grinding
+const GRINDING_BATCH_SIZE: u64 = 1 << 12;
+
+// Fiat-Shamir channel, the state being chained with the hash function H
+// It only holds the state: the messages themselves are carried by the typed proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel<H: Hasher = Sha256> {
+    pub state: [u8; 32],
+    hasher: PhantomData<H>,
 }
 
-impl Member {
-    pub fn new(member_type: Type, data: Vec<u8>) -> Self {
-        Self { member_type, data }
+impl<H: Hasher> Default for Channel<H> {
+    fn default() -> Self {
+        Self {
+            state: [0u8; 32],
+            hasher: PhantomData,
+        }
     }
 }
 
-impl fmt::Display for Member {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let encoded_data = encode(&self.data); // Apply encode to data
-        write!(f, "{{{:?}:{}}}", self.member_type, encoded_data)
+impl Channel {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl fmt::Debug for Member {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let encoded_data = encode(&self.data); // Apply encode to data
-        write!(f, "{{{:?}:{}}}", self.member_type, encoded_data)
+impl<H: Hasher> Channel<H> {
+    // The state becomes H(SEND_PREFIX || state || data)
+    pub fn send(&mut self, data: &Vec<u8>) {
+        self.state = H::hash(&[&[SEND_PREFIX], self.state.as_slice(), data.as_slice()].concat());
     }
-}
-
-fn serialize_hex_array<S>(data: &Vec<u8>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    let compact = encode(data); // Get hex-encoded string
-    serializer.serialize_str(&compact)
-}
 
-fn deserialize_hex_array<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let hex_string: String = String::deserialize(deserializer)?; // Deserialize as a regular string
-    decode(&hex_string).map_err(serde::de::Error::custom) // Decode the hex string to Vec<u8>
-}
+    // Runs a draw on the stream of bytes squeezed from the current state, then updates the state once, however many
+    // bytes the draw consumed
+    fn draw<T>(&mut self, f: impl FnOnce(&mut Squeezer<H>) -> T) -> T {
+        let mut squeezer = Squeezer::new(self.state);
+        let result = f(&mut squeezer);
+        self.state = H::hash(&self.state);
+        result
+    }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Channel {
-    pub state: [u8; 32],
-    pub proof: Vec<Member>,
-}
+    pub fn receive_random_field_element(&mut self) -> MyField {
+        self.receive_random_field_elements(1)[0]
+    }
 
-impl Channel {
-    pub fn new() -> Self {
-        Self {
-            state: [0u8; 32],
-            proof: vec![],
-        }
+    // Draws several field elements from one state update
+    pub fn receive_random_field_elements(&mut self, n: usize) -> Vec<MyField> {
+        self.draw(|squeezer| (0..n).map(|_| squeezer.field_element()).collect())
     }
 
-    pub fn send(&mut self, data: &Vec<u8>) {
-        self.state = Sha256::hash(&concat_slices(&self.state, &data.as_slice()).as_slice());
-        self.proof.push(Member {
-            member_type: Type::Send,
-            data: data.to_vec(),
-        });
+    pub fn receive_random_extension_field_element(&mut self) -> MyFieldExt {
+        self.receive_random_extension_field_elements(1)[0]
     }
 
-    pub fn receive_random_field_element(&mut self) -> MyField {
-        let modulus: BigUint = MyField::MODULUS.into();
-        let random_number = BigUint::from_bytes_be(&self.state) % modulus;
-        let random_field_element = MyField::from(random_number.to_u64().unwrap());
-        self.state = Sha256::hash(&self.state);
-        self.proof.push(Member {
-            member_type: Type::Receive,
-            data: random_field_element.into_bigint().to_bytes_le().to_vec(),
-        });
-        random_field_element
+    // Draws several elements of the extension field from one state update
+    pub fn receive_random_extension_field_elements(&mut self, n: usize) -> Vec<MyFieldExt> {
+        let degree = MyFieldExt::extension_degree() as usize;
+        self.draw(|squeezer| {
+            (0..n)
+                .map(|_| {
+                    let coefficients = (0..degree).map(|_| squeezer.field_element());
+                    MyFieldExt::from_base_prime_field_elems(coefficients).unwrap()
+                })
+                .collect()
+        })
     }
 
+    // Draws an integer uniformly in [min, max]
     pub fn receive_random_int(&mut self, min: u64, max: u64) -> u64 {
-        let random_int = (min + BigUint::from_bytes_be(&self.state) % (max - min + 1))
-            .to_u64()
-            .unwrap();
-        self.state = Sha256::hash(&self.state);
-        self.proof.push(Member {
-            member_type: Type::Receive,
-            data: random_int.to_le_bytes().to_vec(),
-        });
-        random_int
+        assert!(min <= max, "min must not exceed max");
+        self.draw(|squeezer| min + squeezer.int_below((max - min) as u128 + 1))
     }
-}
 
-pub fn parse_sent_root(member: &Member) -> [u8; 32] {
-    assert_eq!(member.member_type, Type::Send, "Type must be Send");
-    let root: [u8; 32] = match member.data.clone().try_into() {
-        Ok(arr) => arr,
-        Err(_) => panic!("Data must have exactly 32 bytes"),
-    };
+    // Draws num_ids distinct integers uniformly in [0, bound) from one state update, in the order of their first draw
+    pub fn receive_random_distinct_ints(&mut self, num_ids: usize, bound: u64) -> Vec<u64> {
+        assert!(
+            num_ids as u64 <= bound,
+            "Cannot draw more distinct integers than the size of the range"
+        );
+        self.draw(|squeezer| {
+            let mut drawn = BTreeSet::new();
+            let mut random_ints = vec![];
+            while random_ints.len() < num_ids {
+                let random_int = squeezer.int_below(bound as u128);
+                if drawn.insert(random_int) {
+                    random_ints.push(random_int);
+                }
+            }
+            random_ints
+        })
+    }
 
-    root
-}
+    // Draws the ids of num_queries distinct queries over an eval domain of size eval_domain_size
+    pub fn receive_random_query_ids(
+        &mut self,
+        num_queries: usize,
+        eval_domain_size: usize,
+    ) -> Vec<usize> {
+        self.receive_random_distinct_ints(num_queries, eval_domain_size as u64)
+            .into_iter()
+            .map(|id| id as usize)
+            .collect()
+    }
 
-pub fn parse_received_field_element(member: &Member) -> MyField {
-    assert_eq!(member.member_type, Type::Receive, "Type must be Receive");
-    let bytes: [u8; 8] = match member.data.clone().try_into() {
-        Ok(arr) => arr,
-        Err(_) => panic!("Data must have exactly 8 bytes"),
-    };
+    // Number of leading zero bits of H(PROOF_OF_WORK_PREFIX || state || nonce), the nonce being encoded as 8
+    // little-endian bytes
+    fn proof_of_work_bits(&self, nonce: u64) -> u32 {
+        let digest = H::hash(
+            &[
+                &[PROOF_OF_WORK_PREFIX],
+                self.state.as_slice(),
+                &nonce.to_le_bytes(),
+            ]
+            .concat(),
+        );
+        match digest.iter().position(|&byte| byte != 0) {
+            Some(i) => 8 * i as u32 + digest[i].leading_zeros(),
+            None => 256,
+        }
+    }
 
-    MyField::from(u64::from_le_bytes(bytes))
-}
+    // Searches the smallest nonce whose proof-of-work has grinding_bits leading zero bits, and sends it
+    // The nonces are split into consecutive batches of GRINDING_BATCH_SIZE searched one after the other, each batch being
+    // searched in parallel, so that all the workers hash nonces which can be the smallest solution.
+    pub fn grind(&mut self, grinding_bits: u32) -> u64 {
+        let nonce = (0..u64::MAX / GRINDING_BATCH_SIZE)
+            .find_map(|batch| {
+                (batch * GRINDING_BATCH_SIZE..(batch + 1) * GRINDING_BATCH_SIZE)
+                    .into_par_iter()
+                    .find_first(|&nonce| self.proof_of_work_bits(nonce) >= grinding_bits)
+            })
+            .expect("No nonce solves the proof-of-work");
+        self.send(&nonce.to_le_bytes().to_vec());
+        nonce
+    }
 
-pub fn parse_sent_field_element(member: &Member) -> MyField {
-    assert_eq!(member.member_type, Type::Send, "Type must be Send");
-    let bytes: [u8; 8] = match member.data.clone().try_into() {
-        Ok(arr) => arr,
-        Err(_) => panic!("Data must have exactly 8 bytes"),
-    };
+    // Checks that the nonce claimed by the prover solves the proof-of-work, and absorbs it as the prover did
+    pub fn replay_proof_of_work(
+        &mut self,
+        nonce: u64,
+        grinding_bits: u32,
+    ) -> Result<(), VerifierError> {
+        if self.proof_of_work_bits(nonce) < grinding_bits {
+            return Err(VerifierError::ProofOfWorkMismatch);
+        }
+        self.send(&nonce.to_le_bytes().to_vec());
+        Ok(())
+    }
+}
 
-    MyField::from(u64::from_le_bytes(bytes))
+// Stream of bytes H(SQUEEZE_PREFIX || state || 0) || H(SQUEEZE_PREFIX || state || 1) || .. from which the random
+// challenges are derived, the counters being encoded as 8 little-endian bytes
+struct Squeezer<H: Hasher> {
+    state: [u8; 32],
+    counter: u64,
+    buffer: Vec<u8>,
+    hasher: PhantomData<H>,
 }
 
-pub fn parse_received_int(member: &Member) -> u64 {
-    assert_eq!(member.member_type, Type::Receive, "Type must be Receive");
-    let bytes: [u8; 8] = match member.data.clone().try_into() {
-        Ok(arr) => arr,
-        Err(_) => panic!("Data must have exactly 8 bytes"),
-    };
+impl<H: Hasher> Squeezer<H> {
+    fn new(state: [u8; 32]) -> Self {
+        Self {
+            state,
+            counter: 0,
+            buffer: vec![],
+            hasher: PhantomData,
+        }
+    }
 
-    u64::from_le_bytes(bytes)
-}
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        while self.buffer.len() < N {
+            let block = H::hash(
+                &[
+                    &[SQUEEZE_PREFIX],
+                    self.state.as_slice(),
+                    &self.counter.to_le_bytes(),
+                ]
+                .concat(),
+            );
+            self.buffer.extend_from_slice(&block);
+            self.counter += 1;
+        }
+        self.buffer
+            .drain(..N)
+            .collect::<Vec<u8>>()
+            .try_into()
+            .unwrap()
+    }
+
+    // Wide reduction of 128 bits modulo p: the statistical distance to the uniform distribution is below
+    // p / 2^128 < 2^-96
+    fn field_element(&mut self) -> MyField {
+        let modulus = MyField::MODULUS.0[0] as u128;
+        let random_number = u128::from_le_bytes(self.bytes()) % modulus;
+        MyField::from(random_number as u64)
+    }
 
-pub fn parse_sent_authentication_path(member: &Member) -> Vec<[u8; 32]> {
-    assert_eq!(member.member_type, Type::Send, "Type must be Send");
-    assert!(
-        member.data.len() % 32 == 0,
-        "Data must be a multiple of 32 bytes"
-    );
-
-    member
-        .data
-        .chunks(32)
-        .map(|chunk| chunk.try_into().expect("Chunk should have 32 bytes"))
-        .collect()
+    // Rejection sampling of 64 bits: values in the incomplete last interval of length bound are drawn again, so that
+    // the result is exactly uniform in [0, bound), bound being in [1, 2^64]
+    fn int_below(&mut self, bound: u128) -> u64 {
+        assert!((1..=1 << 64).contains(&bound), "Bound must be in [1, 2^64]");
+        let limit = (1u128 << 64) / bound * bound;
+        loop {
+            let random_number = u64::from_le_bytes(self.bytes()) as u128;
+            if random_number < limit {
+                return (random_number % bound) as u64;
+            }
+        }
+    }
 }
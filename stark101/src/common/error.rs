@@ -0,0 +1,81 @@
+use std::fmt;
+
+// Reasons for which the verifier rejects a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifierError {
+    // The proof is not shaped as expected from the AIR and the options (missing queries, wrong sizes, bad encoding, ..)
+    MalformedProof(String),
+    // The options of the verifier are not valid for the AIR, see ProofOptions::check
+    InvalidOptions(String),
+    // The proof was generated with other options than the ones of the verifier
+    OptionsMismatch,
+    // A random challenge claimed by the prover is not the one derived from the channel
+    ChallengeMismatch,
+    // The nonce of the prover does not solve the proof-of-work drawn from the channel
+    ProofOfWorkMismatch,
+    // The rows of the trace opened by the queries are not in the committed trace
+    TraceMerkleProofMismatch,
+    // The elements of a FRI layer opened by the queries are not in the committed layer (layer 0 being CP)
+    MerkleProofMismatch { layer: usize },
+    // The elements of a batched evaluation vector opened by the queries are not in its committed tree
+    BatchedMerkleProofMismatch { function: usize },
+    // The remainder polynomial has nonzero coefficients beyond the degree bound of the last FRI layer
+    RemainderDegreeMismatch { degree_bound: usize },
+    // The elements of a FRI layer of a query do not fold into the element of the next layer
+    FriFoldingMismatch { layer: usize, query: usize },
+    // The CP element of a query is not the combination of the constraints evaluated on the trace rows
+    ConstraintMismatch { query: usize },
+}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifierError::MalformedProof(reason) => write!(f, "malformed proof: {}", reason),
+            VerifierError::InvalidOptions(reason) => write!(f, "invalid options: {}", reason),
+            VerifierError::OptionsMismatch => {
+                write!(f, "proof was generated with different options")
+            }
+            VerifierError::ChallengeMismatch => {
+                write!(
+                    f,
+                    "challenge does not match the one derived from the channel"
+                )
+            }
+            VerifierError::ProofOfWorkMismatch => {
+                write!(f, "nonce does not solve the proof-of-work")
+            }
+            VerifierError::TraceMerkleProofMismatch => {
+                write!(f, "check of decommitment of trace rows failed")
+            }
+            VerifierError::MerkleProofMismatch { layer } => {
+                write!(f, "check of decommitment of FRI layer {} failed", layer)
+            }
+            VerifierError::BatchedMerkleProofMismatch { function } => {
+                write!(
+                    f,
+                    "check of decommitment of batched function {} failed",
+                    function
+                )
+            }
+            VerifierError::RemainderDegreeMismatch { degree_bound } => {
+                write!(
+                    f,
+                    "remainder polynomial does not have degree less than {}",
+                    degree_bound
+                )
+            }
+            VerifierError::FriFoldingMismatch { layer, query } => write!(
+                f,
+                "evaluations of FRI layer {} do not satisfy the recurrence relation at query {}",
+                layer, query
+            ),
+            VerifierError::ConstraintMismatch { query } => write!(
+                f,
+                "CP evaluation at query {} does not match the trace decommitment",
+                query
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifierError {}
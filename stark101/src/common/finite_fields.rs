@@ -1,7 +1,53 @@
-use ark_ff::fields::{Fp64, MontBackend, MontConfig};
+use ark_ff::fields::{Fp3, Fp3Config, Fp64, MontBackend, MontConfig};
+use ark_ff::{Field, MontFp, PrimeField};
 
 #[derive(MontConfig)]
 #[modulus = "3221225473"]
 #[generator = "5"]
 pub struct MyFieldConfig;
 pub type MyField = Fp64<MontBackend<MyFieldConfig, 1>>;
+
+// Number of bytes of the canonical encoding of an element of MyField
+pub const FIELD_ELEMENT_BYTES: usize = 8;
+
+// Canonical fixed-width encoding of an element of MyField: its representative in [0, p) as little-endian bytes
+pub fn field_element_to_bytes(element: &MyField) -> [u8; FIELD_ELEMENT_BYTES] {
+    element.into_bigint().0[0].to_le_bytes()
+}
+
+// Decodes an element of MyField, rejecting non-canonical encodings
+pub fn field_element_from_bytes(bytes: [u8; FIELD_ELEMENT_BYTES]) -> Option<MyField> {
+    MyField::from_bigint(u64::from_le_bytes(bytes).into())
+}
+
+// Cubic extension MyField[X] / (X^3 - 5) from which the random challenges are drawn, MyField being too small for the
+// soundness of the protocol (5 is a cubic non-residue since it generates the multiplicative group and 3 | p - 1)
+pub struct MyFieldExtConfig;
+pub type MyFieldExt = Fp3<MyFieldExtConfig>;
+
+impl Fp3Config for MyFieldExtConfig {
+    type Fp = MyField;
+
+    const NONRESIDUE: MyField = MontFp!("5");
+
+    // NONRESIDUE^((p^i - 1) / 3) and NONRESIDUE^(2 * (p^i - 1) / 3) for i = 0, 1, 2
+    const FROBENIUS_COEFF_FP3_C1: &'static [MyField] =
+        &[MontFp!("1"), MontFp!("1610563584"), MontFp!("1610661888")];
+    const FROBENIUS_COEFF_FP3_C2: &'static [MyField] =
+        &[MontFp!("1"), MontFp!("1610661888"), MontFp!("1610563584")];
+
+    // p^3 - 1 = 2^30 * t
+    const TWO_ADICITY: u32 = 30;
+    const TRACE_MINUS_ONE_DIV_TWO: &'static [u64] = &[0xd800000360000004];
+    // 5^t, 5 being a quadratic non-residue of MyField and thus of its extension of odd degree
+    const QUADRATIC_NONRESIDUE_TO_T: MyFieldExt =
+        MyFieldExt::new(MontFp!("1953125"), MontFp!("0"), MontFp!("0"));
+}
+
+// Coefficients of elements of the extension field, concatenated
+pub fn flatten_extension_field_elements(elements: &[MyFieldExt]) -> Vec<MyField> {
+    elements
+        .iter()
+        .flat_map(|element| element.to_base_prime_field_elements())
+        .collect()
+}
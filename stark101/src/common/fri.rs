@@ -1,258 +1,766 @@
-use std::iter::zip;
-
 use crate::common::{
     channel::Channel,
-    finite_fields::MyField,
-    merkle::{create_merkle_tree, get_authentication_path},
+    error::VerifierError,
+    finite_fields::{flatten_extension_field_elements, MyField, MyFieldExt},
+    hasher::Hasher,
+    merkle::{
+        create_merkle_tree_from_columns, get_merkle_cap, get_multi_proof, hash_leaf, MerkleTree,
+    },
+    polynomials::{coset, coset_element, interpolate_extension_on_coset},
+    proof::{
+        extension_field_elements_to_bytes, field_elements_to_bytes, FriLayerOpening,
+        FriQueryOpening, MerkleCap, MerkleProof, QueryOpening, TraceOpening,
+    },
 };
-use ark_ff::{BigInteger, Field, PrimeField};
-use ark_poly::{univariate::DensePolynomial, Polynomial};
-use rs_merkle::{algorithms::Sha256, MerkleTree};
+use ark_ff::{batch_inversion, AdditiveGroup, Field};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+
+use super::merkle::{merkle_cap_len, verify_multi_decommitment};
+
+// The FRI layers, Merkle trees and remainder produced by the commit phase
+// The layers are in the extension field. All the layers but the last one have a Merkle tree, the last layer being
+// sent as the coefficients of the remainder polynomial.
+pub type FriCommitments<H> = (Vec<Vec<MyFieldExt>>, Vec<MerkleTree<H>>, Vec<MyFieldExt>);
+
+// Evaluation vector on the eval domain with the tree committing to it, which may be built by the caller
+// The tree must have the layout of the first FRI layer: its i-th leaf holds the folding_factors[0] elements at
+// i + j * len / folding_factors[0], see create_fri_merkle_tree.
+pub type CommittedEvaluations<'a, H> = (&'a [MyFieldExt], &'a MerkleTree<H>);
+
+// Folding factors of the FRI layers reducing a polynomial of degree less than degree_bound to a remainder of degree
+// less than remainder_degree_bound: each layer folds by folding_factor but the last one, which folds by the remaining
+// ratio when it is smaller
+pub fn fri_folding_factors(
+    degree_bound: usize,
+    folding_factor: usize,
+    remainder_degree_bound: usize,
+) -> Vec<usize> {
+    let mut folding_factors = vec![];
+    let mut degree_bound = degree_bound;
+    while degree_bound > remainder_degree_bound {
+        let factor = folding_factor.min(degree_bound / remainder_degree_bound);
+        folding_factors.push(factor);
+        degree_bound /= factor;
+    }
+    folding_factors
+}
+
+// Number of elements in a leaf of the tree of the given FRI layer: the folding factor of the layer, or 1 when CP is
+// not folded at all
+pub fn fri_leaf_size(folding_factors: &[usize], layer: usize) -> usize {
+    folding_factors.get(layer).copied().unwrap_or(1)
+}
+
+// Coefficients of the remainder polynomial interpolated from the last FRI layer on the coset of the given offset,
+// padded with zeros to exactly max_remainder_degree + 1 coefficients
+pub fn fri_remainder(
+    last_layer: &[MyFieldExt],
+    offset: MyField,
+    max_remainder_degree: usize,
+) -> Vec<MyFieldExt> {
+    let poly = interpolate_extension_on_coset(offset, last_layer);
+    assert!(
+        poly.degree() <= max_remainder_degree,
+        "The degree of the remainder must be at most {}",
+        max_remainder_degree
+    );
+    let mut coeffs = poly.coeffs;
+    coeffs.resize(max_remainder_degree + 1, MyFieldExt::ZERO);
+    coeffs
+}
+
+// Commits to a FRI layer, the i-th leaf holding the folding_factor elements at x w^j, i.e. at i + j * len /
+// folding_factor, so that a single path opens the whole coset of a query (a layer shorter than folding_factor has a
+// single leaf)
+// A leaf hashes the coefficients of all the elements.
+pub fn create_fri_merkle_tree<H: Hasher>(
+    layer: &[MyFieldExt],
+    folding_factor: usize,
+) -> MerkleTree<H> {
+    let columns: Vec<Vec<MyField>> = layer
+        .chunks(layer.len().div_ceil(folding_factor))
+        .flat_map(|half| {
+            (0..MyFieldExt::extension_degree() as usize).map(move |k| {
+                half.iter()
+                    .map(|element| element.to_base_prime_field_elements().nth(k).unwrap())
+                    .collect()
+            })
+        })
+        .collect();
+    create_merkle_tree_from_columns(&columns)
+}
 
-use super::merkle::verify_decommitment;
+// Leaf of the tree of a FRI layer holding the coset of the element at id, with the row of the leaf
+fn fri_leaf(
+    id: usize,
+    layer_len: usize,
+    folding_factor: usize,
+    opening: &FriLayerOpening,
+) -> (usize, Vec<MyField>) {
+    let (leaf_id, _) = fri_layer_ids(id, layer_len, folding_factor);
+    (leaf_id, flatten_extension_field_elements(&opening.values))
+}
 
-// Computes the subsequent FRI domain by taking the first half of the current FRI domain (dropping the second half),
-// and squaring each of its elements.
-pub fn compute_next_fri_domain(fri_domain: &Vec<MyField>) -> Vec<MyField> {
-    let next_fri_domain_len = fri_domain.len() / 2;
+// Computes the subsequent FRI domain by taking the first 1 / folding_factor of the current FRI domain and raising
+// each of its elements to the power folding_factor, the elements of a coset x w^j all having the same power.
+pub fn compute_next_fri_domain(fri_domain: &[MyField], folding_factor: usize) -> Vec<MyField> {
+    let next_fri_domain_len = fri_domain.len() / folding_factor;
     fri_domain[..next_fri_domain_len]
         .iter()
-        .map(|x| x.pow(&(vec![2])))
+        .map(|x| x.pow([folding_factor as u64]))
         .collect()
 }
 
 // Computes subsequent FRI polynomial by
-// 1. Getting a random field element
-// 2. Multiplying the odd coefficients of the previous polynomial by
-// 3. Summing together consecutive pairs (even-odd) of coefficients.
+// 1. Getting a random field element beta
+// 2. Splitting the previous polynomial into P_0(x^k) + x P_1(x^k) + .. + x^(k-1) P_(k-1)(x^k), k being the folding
+// factor, i.e. into the cosets of its coefficients modulo k
+// 3. Summing together the P_j multiplied by beta^j.
 pub fn compute_next_fri_polynomial(
-    poly: &DensePolynomial<MyField>,
-    beta: MyField,
-) -> DensePolynomial<MyField> {
-    let even_coeffs: Vec<MyField> = poly.coeffs.iter().step_by(2).cloned().collect();
-    let odd_coeffs: Vec<MyField> = poly.coeffs.iter().skip(1).step_by(2).cloned().collect();
-    let even = DensePolynomial::<MyField> {
-        coeffs: even_coeffs,
-    };
-    let odd = DensePolynomial::<MyField> { coeffs: odd_coeffs };
-    &even + (&odd * beta)
-}
-
-// Computes next FRI layer by taking a polynomial, a domain, and a field element and returns the next polynomial,
-// the next domain, and the evaluation of this next polynomial on this next domain.
+    poly: &DensePolynomial<MyFieldExt>,
+    beta: MyFieldExt,
+    folding_factor: usize,
+) -> DensePolynomial<MyFieldExt> {
+    let mut next_coeffs = vec![MyFieldExt::ZERO; poly.coeffs.len().div_ceil(folding_factor)];
+    let mut beta_power = MyFieldExt::ONE;
+    for j in 0..folding_factor {
+        for (next_coeff, coeff) in next_coeffs
+            .iter_mut()
+            .zip(poly.coeffs.iter().skip(j).step_by(folding_factor))
+        {
+            *next_coeff += *coeff * beta_power;
+        }
+        beta_power *= beta;
+    }
+    DensePolynomial::from_coefficients_vec(next_coeffs)
+}
+
+// Inverses of the points of the first half of the eval domain, the twiddles of the folding of all the FRI layers
+// The i-th point of a FRI layer is the i-th point x_i of the eval domain raised to the power K, the ratio of their
+// lengths, so its inverse is the one of x_(iK) times x_0^(1-K).
+pub fn fri_inverse_twiddles(eval_domain: &[MyField]) -> Vec<MyField> {
+    let mut inverse_twiddles = eval_domain[..eval_domain.len() / 2].to_vec();
+    batch_inversion(&mut inverse_twiddles);
+    inverse_twiddles
+}
+
+// Computes next FRI layer directly from the evaluations of the current one on a coset of the eval domain
+// Folding by 2 maps the evaluations at x and -x, which lie half a layer apart, to the evaluation at x^2
+// f_next(x^2) = (f(x) + f(-x)) / 2 + beta (f(x) - f(-x)) / 2x
+// Folding by 2^m with beta amounts to folding m times by 2 with beta, beta^2, .. beta^(2^(m-1)), so that each layer
+// costs O(n) with no polynomial in coefficient form.
 pub fn compute_next_fri_layer(
-    poly: &DensePolynomial<MyField>,
-    domain: &Vec<MyField>,
-    beta: MyField,
-) -> (DensePolynomial<MyField>, Vec<MyField>, Vec<MyField>) {
-    let next_poly = compute_next_fri_polynomial(poly, beta);
-    let next_domain = compute_next_fri_domain(domain);
-    let next_layer = next_domain
+    layer: &[MyFieldExt],
+    inverse_twiddles: &[MyField],
+    beta: MyFieldExt,
+    folding_factor: usize,
+) -> Vec<MyFieldExt> {
+    let two_inv = MyField::from(2).inverse().unwrap();
+    let mut layer = layer.to_vec();
+    let mut beta = beta;
+    for _ in 0..folding_factor.trailing_zeros() {
+        let half_len = layer.len() / 2;
+        let power = inverse_twiddles.len() / half_len;
+        let offset_inv = inverse_twiddles[0].pow([power as u64 - 1]);
+        layer = (0..half_len)
+            .map(|i| {
+                let (f_x, f_minus_x) = (layer[i], layer[i + half_len]);
+                let x_inv = inverse_twiddles[i * power] * offset_inv;
+                (f_x + f_minus_x + beta * (f_x - f_minus_x).mul_by_base_prime_field(&x_inv))
+                    .mul_by_base_prime_field(&two_inv)
+            })
+            .collect();
+        beta.square_in_place();
+    }
+    layer
+}
+
+// Computes the FRI layers, the FRI Merkle trees and the remainder
+// The tree of the first layer, whose leaves must hold folding_factors[0] elements, is moved into the FRI Merkle trees
+// rather than copied, ahead of the trees of the folded layers (see fold_fri_layers).
+pub fn generate_fri_commitments<H: Hasher>(
+    eval_domain: &[MyField],
+    poly_eval: &[MyFieldExt],
+    poly_merkle: MerkleTree<H>,
+    folding_factors: &[usize],
+    max_remainder_degree: usize,
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> FriCommitments<H> {
+    let (fri_layers, mut fri_merkles, remainder) = fold_fri_layers(
+        eval_domain,
+        poly_eval.to_vec(),
+        folding_factors,
+        max_remainder_degree,
+        cap_height,
+        channel,
+    );
+    fri_merkles.insert(0, poly_merkle);
+    (fri_layers, fri_merkles, remainder)
+}
+
+// Folds the first FRI layer down to the remainder
+// The method contains a loop, in each iteration of which we fold the last layer by the folding factor of the layer
+// (see fri_folding_factors), so that the last layer is the evaluation of a polynomial of degree at most
+// max_remainder_degree. Its coefficients are sent in place of a commitment to the last layer. Each other folded layer
+// is committed by the Merkle cap of height cap_height of its tree. The first layer is left to the caller to commit.
+pub fn fold_fri_layers<H: Hasher>(
+    eval_domain: &[MyField],
+    first_layer: Vec<MyFieldExt>,
+    folding_factors: &[usize],
+    max_remainder_degree: usize,
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> FriCommitments<H> {
+    let inverse_twiddles = fri_inverse_twiddles(eval_domain);
+    let mut fri_layers = vec![first_layer];
+    let mut fri_merkles = vec![];
+    for (layer, &folding_factor) in folding_factors.iter().enumerate() {
+        let beta = channel.receive_random_extension_field_element();
+        fri_layers.push(compute_next_fri_layer(
+            fri_layers.last().unwrap(),
+            &inverse_twiddles,
+            beta,
+            folding_factor,
+        ));
+        if layer + 1 < folding_factors.len() {
+            fri_merkles.push(create_fri_merkle_tree(
+                fri_layers.last().unwrap(),
+                fri_leaf_size(folding_factors, layer + 1),
+            ));
+            channel.send(
+                &MerkleCap(get_merkle_cap(fri_merkles.last().unwrap(), cap_height)).to_bytes(),
+            );
+        }
+    }
+    // The last layer lies on the eval domain raised to the power of the product of the folding factors
+    let last_layer = fri_layers.last().unwrap();
+    let offset = eval_domain[0].pow([(eval_domain.len() / last_layer.len()) as u64]);
+    let remainder = fri_remainder(last_layer, offset, max_remainder_degree);
+    channel.send(&extension_field_elements_to_bytes(&remainder));
+    (fri_layers, fri_merkles, remainder)
+}
+
+// Random linear combination of several evaluation vectors on the same domain with the given coefficients
+pub fn batch_fri_evaluations<E: AsRef<[MyFieldExt]>>(
+    evaluations: &[E],
+    coefficients: &[MyFieldExt],
+) -> Vec<MyFieldExt> {
+    let mut batched = vec![MyFieldExt::ZERO; evaluations[0].as_ref().len()];
+    for (evaluation, coefficient) in evaluations.iter().zip(coefficients) {
+        for (batched_value, value) in batched.iter_mut().zip(evaluation.as_ref()) {
+            *batched_value += *value * coefficient;
+        }
+    }
+    batched
+}
+
+// Computes the FRI layers, the FRI Merkle trees and the remainder proving that several evaluation vectors on the eval
+// domain are all close to polynomials of degree less than the same bound
+// The vectors, each already committed by its own tree, are combined with one random coefficient each into the first
+// FRI layer, which is not committed: the verifier recomputes its cosets from the ones of the vectors. Returns the
+// coefficients ahead of the FRI layers, the trees of the folded layers and the remainder.
+pub fn generate_batched_fri_commitments<H: Hasher>(
+    eval_domain: &[MyField],
+    committed: &[CommittedEvaluations<H>],
+    folding_factors: &[usize],
+    max_remainder_degree: usize,
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> (Vec<MyFieldExt>, FriCommitments<H>) {
+    // The trees are checked to have one leaf per coset of the first layer, and their leaves to hold the cosets when
+    // they are opened
+    for (evaluations, merkle) in committed {
+        assert_eq!(
+            evaluations.len(),
+            eval_domain.len(),
+            "Batched vectors must be evaluated on the eval domain"
+        );
+        assert_eq!(
+            merkle.leaves_len(),
+            eval_domain.len() / folding_factors[0],
+            "Batched trees must have one leaf per coset of {} elements",
+            folding_factors[0]
+        );
+    }
+    let evaluations: Vec<&[MyFieldExt]> = committed
         .iter()
-        .map(|point| next_poly.evaluate(&point))
+        .map(|(evaluations, _)| *evaluations)
         .collect();
-    (next_poly, next_domain, next_layer)
-}
-
-// Computes the FRI polynomials, the FRI domains, the FRI layers and the FRI Merkle trees
-// The method contains a loop, in each iteration of which we extend these four lists, using the last element in each.
-// The iteration should stop once the last FRI polynomial is of degree 0, that is - when the last FRI polynomial is just
-// a constant.
-pub fn generate_fri_commitments(
-    poly: &DensePolynomial<MyField>,
-    poly_domain: &Vec<MyField>,
-    poly_eval: &Vec<MyField>,
-    poly_merkle: &MerkleTree<Sha256>,
-    channel: &mut Channel,
-) -> (
-    Vec<DensePolynomial<MyField>>,
-    Vec<Vec<MyField>>,
-    Vec<Vec<MyField>>,
-    Vec<MerkleTree<Sha256>>,
-) {
-    let mut fri_polys = vec![poly.clone()];
-    let mut fri_domains = vec![poly_domain.clone()];
-    let mut fri_layers = vec![poly_eval.clone()];
-    let mut fri_merkles = vec![poly_merkle.clone()];
-    while fri_polys.last().unwrap().degree() > 0 {
-        let beta = channel.receive_random_field_element();
-        let (next_poly, next_domain, next_layer) =
-            compute_next_fri_layer(fri_polys.last().unwrap(), fri_domains.last().unwrap(), beta);
-        fri_polys.push(next_poly);
-        fri_domains.push(next_domain);
-        fri_layers.push(next_layer);
-        fri_merkles.push(create_merkle_tree(&fri_layers.last().unwrap()));
-        channel.send(&fri_merkles.last().unwrap().root().unwrap().to_vec());
-    }
-    channel.send(
-        &fri_polys.last().unwrap().coeffs[0]
-            .into_bigint()
-            .to_bytes_le(),
+    let coefficients = channel.receive_random_extension_field_elements(evaluations.len());
+    let fri_commitments = fold_fri_layers(
+        eval_domain,
+        batch_fri_evaluations(&evaluations, &coefficients),
+        folding_factors,
+        max_remainder_degree,
+        cap_height,
+        channel,
     );
-    (fri_polys, fri_domains, fri_layers, fri_merkles)
+    (coefficients, fri_commitments)
 }
 
-// Decommits on FRI layers given an index by sending the following data
-// 1. The element of the FRI layer at the given index (using fri_layers).
-// 2. Its authentication path (using the corresponding Merkle tree from fri_merkles).
-// 3. The element's FRI sibling
-// 4. The authentication path of the element's sibling (using the same merkle tree).
-pub fn decommit_on_fri_layers(
+// Ids of the rows of the trace at x, gx, .. g^(frame_size-1)x, where g^k x lies k * row_offset positions after x in the
+// evaluation domain, wrapping around its end
+pub fn trace_frame_ids(
     id: usize,
-    fri_layers: &Vec<Vec<MyField>>,
-    fri_merkles: &Vec<MerkleTree<Sha256>>,
-    channel: &mut Channel,
-) {
-    for (layer, merkle) in zip(
-        &fri_layers[..fri_layers.len() - 1],
-        &fri_merkles[..fri_merkles.len() - 1],
-    ) {
-        let id = id % layer.len();
-        let sibling_id = (id + layer.len() / 2) % layer.len();
-        channel.send(&layer[id].into_bigint().to_bytes_le()); // The element from the current layer
-        channel.send(
-            &get_authentication_path(&merkle, id)
-                .iter()
-                .flat_map(|arr| arr.to_vec())
-                .collect(),
-        ); // The authentication path for this element
-        channel.send(&layer[sibling_id].into_bigint().to_bytes_le()); // The element's sibling in the current layer
-        channel.send(
-            &get_authentication_path(&merkle, sibling_id)
-                .iter()
-                .flat_map(|arr| arr.to_vec())
+    row_offset: usize,
+    frame_size: usize,
+    eval_domain_size: usize,
+) -> Vec<usize> {
+    (0..frame_size)
+        .map(|k| (id + k * row_offset) % eval_domain_size)
+        .collect()
+}
+
+// Id of the leaf of the tree of a FRI layer of length layer_len holding the coset of the element at a query, and
+// position of the element in the leaf
+pub fn fri_layer_ids(id: usize, layer_len: usize, folding_factor: usize) -> (usize, usize) {
+    let num_leaves = layer_len / folding_factor;
+    let id = id % layer_len;
+    (id % num_leaves, id / num_leaves)
+}
+
+// Decommits on FRI layers given an index by sending the coset of the element of each FRI layer at the given index,
+// i.e. the elements folding together into the element of the next layer (using fri_layers)
+// Returns the openings of all the layers but the last one, which the verifier evaluates from the remainder.
+pub fn decommit_on_fri_layers<H: Hasher>(
+    id: usize,
+    fri_layers: &[Vec<MyFieldExt>],
+    channel: &mut Channel<H>,
+) -> Vec<FriLayerOpening> {
+    let mut openings = vec![];
+    for (layer, next_layer) in fri_layers.iter().zip(&fri_layers[1..]) {
+        let num_leaves = next_layer.len();
+        let folding_factor = layer.len() / num_leaves;
+        let (leaf_id, _) = fri_layer_ids(id, layer.len(), folding_factor);
+        let opening = FriLayerOpening {
+            values: (0..folding_factor)
+                .map(|j| layer[leaf_id + j * num_leaves])
                 .collect(),
-        ); // The authentication path for the sibling element
+        };
+        channel.send(&extension_field_elements_to_bytes(&opening.values)); // The coset from the current layer
+        openings.push(opening);
+    }
+    openings
+}
+
+// Decommits on batched evaluation vectors given an index by sending the coset of each vector at the index, then the
+// cosets of the folded FRI layers (see decommit_on_fri_layers)
+// The leaf of each tree at the query must hash the coset, otherwise the tree does not have the layout of the first FRI
+// layer (see CommittedEvaluations) and the verifier would reject the opening.
+pub fn decommit_on_batched_query<H: Hasher>(
+    id: usize,
+    committed: &[CommittedEvaluations<H>],
+    fri_layers: &[Vec<MyFieldExt>],
+    channel: &mut Channel<H>,
+) -> FriQueryOpening {
+    let num_leaves = fri_layers[1].len();
+    let folding_factor = fri_layers[0].len() / num_leaves;
+    let (leaf_id, _) = fri_layer_ids(id, fri_layers[0].len(), folding_factor);
+    let batched = committed
+        .iter()
+        .map(|(evaluation, merkle)| {
+            let opening = FriLayerOpening {
+                values: (0..folding_factor)
+                    .map(|j| evaluation[leaf_id + j * num_leaves])
+                    .collect(),
+            };
+            assert_eq!(
+                hash_leaf::<H>(&flatten_extension_field_elements(&opening.values)),
+                merkle.leaves()[leaf_id],
+                "Batched tree does not commit to the coset of the query in its leaf"
+            );
+            channel.send(&extension_field_elements_to_bytes(&opening.values)); // The coset from the vector
+            opening
+        })
+        .collect();
+    FriQueryOpening {
+        id,
+        batched,
+        fri_layers: decommit_on_fri_layers(id, &fri_layers[1..], channel),
     }
-    channel.send(&fri_layers.last().unwrap()[0].into_bigint().to_bytes_le()); // The last element (constant polynomial)
 }
 
-// Decommits on the Trace polynomial by sending the following data
-// The value f(x) with its authentication path.
-// The value f(gx) with its authentication path.
-// The value f(g^2x) with its authentication path.
+// Decommits on the Trace polynomials by sending the following data
+// The row of evaluations of the trace polynomials at x.
+// The row at gx.
+// .. up to the row at g^(frame_size-1)x.
 // Finally, decommits on FRI layers
-pub fn decommit_on_query(
+pub fn decommit_on_query<H: Hasher>(
     id: usize,
-    f_eval: &Vec<MyField>,
-    f_merkle: &MerkleTree<Sha256>,
-    fri_layers: &Vec<Vec<MyField>>,
-    fri_merkles: &Vec<MerkleTree<Sha256>>,
-    channel: &mut Channel,
-) -> () {
-    assert!(id + 16 < f_eval.len());
-    channel.send(&f_eval[id].into_bigint().to_bytes_le()); // f(x)
-    channel.send(
-        &get_authentication_path(&f_merkle, id)
-            .iter()
-            .flat_map(|arr| arr.to_vec())
-            .collect(),
-    ); // authentication path of f(x)
-    channel.send(&f_eval[id + 8].into_bigint().to_bytes_le()); // f(gx)
-    channel.send(
-        &get_authentication_path(&f_merkle, id + 8)
-            .iter()
-            .flat_map(|arr| arr.to_vec())
-            .collect(),
-    ); // authentication path of f(gx)
-    channel.send(&f_eval[id + 16].into_bigint().to_bytes_le()); // f(g^2x)
-    channel.send(
-        &get_authentication_path(&f_merkle, id + 16)
-            .iter()
-            .flat_map(|arr| arr.to_vec())
-            .collect(),
-    ); // authentication path of f(g^2x)
-    decommit_on_fri_layers(id, fri_layers, fri_merkles, channel);
+    row_offset: usize,
+    frame_size: usize,
+    trace_eval: &[Vec<MyField>],
+    fri_layers: &[Vec<MyFieldExt>],
+    channel: &mut Channel<H>,
+) -> QueryOpening {
+    let mut trace_frame = vec![];
+    for row_id in trace_frame_ids(id, row_offset, frame_size, trace_eval[0].len()) {
+        let opening = TraceOpening {
+            row: trace_eval.iter().map(|column| column[row_id]).collect(),
+        };
+        channel.send(&field_elements_to_bytes(&opening.row)); // row at g^k x
+        trace_frame.push(opening);
+    }
+    QueryOpening {
+        id,
+        trace_frame,
+        fri_layers: decommit_on_fri_layers(id, fri_layers, channel),
+    }
 }
 
+// Sends the multi-proofs authenticating the openings of all the queries against the Merkle caps of height cap_height:
+// the one of the trace rows, then the ones of the committed FRI layers, which open a single leaf per query
+pub fn decommit_merkle_proofs<H: Hasher>(
+    ids: &[usize],
+    row_offset: usize,
+    frame_size: usize,
+    trace_merkle: &MerkleTree<H>,
+    fri_merkles: &[MerkleTree<H>],
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> (MerkleProof, Vec<MerkleProof>) {
+    let trace_ids: Vec<usize> = ids
+        .iter()
+        .flat_map(|&id| trace_frame_ids(id, row_offset, frame_size, trace_merkle.leaves_len()))
+        .collect();
+    let trace_proof = MerkleProof(get_multi_proof(trace_merkle, &trace_ids, cap_height));
+    channel.send(&trace_proof.to_bytes());
+    let fri_proofs = decommit_fri_merkle_proofs(ids, fri_merkles, cap_height, channel);
+    (trace_proof, fri_proofs)
+}
+
+// Sends the multi-proofs authenticating the openings of the committed FRI layers at all the queries, a single leaf
+// per query and per layer
+pub fn decommit_fri_merkle_proofs<H: Hasher>(
+    ids: &[usize],
+    fri_merkles: &[MerkleTree<H>],
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> Vec<MerkleProof> {
+    fri_merkles
+        .iter()
+        .map(|merkle| decommit_fri_merkle_proof(ids, merkle, cap_height, channel))
+        .collect()
+}
+
+// Sends the multi-proof authenticating the openings of a tree with the layout of a FRI layer at all the queries
+fn decommit_fri_merkle_proof<H: Hasher>(
+    ids: &[usize],
+    merkle: &MerkleTree<H>,
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> MerkleProof {
+    let layer_ids: Vec<usize> = ids.iter().map(|&id| id % merkle.leaves_len()).collect();
+    let fri_proof = MerkleProof(get_multi_proof(merkle, &layer_ids, cap_height));
+    channel.send(&fri_proof.to_bytes());
+    fri_proof
+}
+
+// Sends the multi-proofs authenticating the openings of all the queries: the ones of the trees of the batched
+// evaluation vectors, then the ones of the committed folded FRI layers
+pub fn decommit_batched_merkle_proofs<H: Hasher>(
+    ids: &[usize],
+    committed: &[CommittedEvaluations<H>],
+    fri_merkles: &[MerkleTree<H>],
+    cap_height: usize,
+    channel: &mut Channel<H>,
+) -> (Vec<MerkleProof>, Vec<MerkleProof>) {
+    let batched_proofs = committed
+        .iter()
+        .map(|(_, merkle)| decommit_fri_merkle_proof(ids, merkle, cap_height, channel))
+        .collect();
+    let fri_proofs = decommit_fri_merkle_proofs(ids, fri_merkles, cap_height, channel);
+    (batched_proofs, fri_proofs)
+}
+
+// Evaluates at beta the polynomial of degree less than points.len() taking the given values at the given points
+// (Lagrange interpolation), which folds the values of a coset of a FRI layer into the element of the next layer
+pub fn fold_coset(points: &[MyField], values: &[MyFieldExt], beta: MyFieldExt) -> MyFieldExt {
+    points
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (&x_i, &value))| {
+            let (numer, denom) = points.iter().enumerate().filter(|&(j, _)| j != i).fold(
+                (MyFieldExt::ONE, MyField::ONE),
+                |(numer, denom), (_, &x_j)| {
+                    (
+                        numer * (beta - MyFieldExt::from_base_prime_field(x_j)),
+                        denom * (x_i - x_j),
+                    )
+                },
+            );
+            value * numer / MyFieldExt::from_base_prime_field(denom)
+        })
+        .sum()
+}
+
+// Value of a query in the opening of a FRI layer of length layer_len
+pub fn fri_opened_value(
+    id: usize,
+    layer_len: usize,
+    folding_factor: usize,
+    opening: &FriLayerOpening,
+) -> MyFieldExt {
+    let (_, position) = fri_layer_ids(id, layer_len, folding_factor);
+    opening.values[position]
+}
+
+// Checks that each layer of the FRI layers openings of a query is the folding of the previous one, the last layer
+// folding into the evaluation of the remainder polynomial at the query
+// The coset of a layer is interpolated at the challenge beta of the layer, which must give the element of the query
+// in the next layer. The eval domain is the coset of the given offset and size, and only the points of the opened
+// cosets are computed, a layer K times shorter than the eval domain lying on the coset of offset^K.
+#[allow(clippy::too_many_arguments)]
 pub fn check_decommittment_on_fri_layers(
-    eval_domain: &Vec<MyField>,
-    betas: &Vec<MyField>,
-    fri_polys_merkle_roots: &Vec<[u8; 32]>,
+    offset: MyField,
+    eval_domain_size: usize,
+    betas: &[MyFieldExt],
+    folding_factors: &[usize],
+    query: usize,
     id: usize,
-    fri_poly_id: &Vec<MyField>,
-    authentication_path_fri_poly_id: &Vec<Vec<[u8; 32]>>,
-    fri_poly_sibling: &Vec<MyField>,
-    authentication_path_fri_poly_sibling: &Vec<Vec<[u8; 32]>>,
-) {
-    let layer_nb = fri_poly_id.len() - 1;
-    let mut layer_len = 8 * 1 << layer_nb;
-    let mut fri_domain = eval_domain.clone();
-    for i in 0..layer_nb {
-        let id = id % layer_len;
-        let sibling_id = (id + (layer_len / 2)) % layer_len;
-        assert!(
-            verify_decommitment(
-                id,
-                fri_poly_id[i],
-                &authentication_path_fri_poly_id[i],
-                fri_polys_merkle_roots[i]
-            ),
-            "check of decommitment of id of round {} in fri_poly failed",
-            i
-        );
-        assert!(
-            verify_decommitment(
-                sibling_id,
-                fri_poly_sibling[i],
-                &authentication_path_fri_poly_sibling[i],
-                fri_polys_merkle_roots[i]
-            ),
-            "check of decommitment of sibling of round {} in fri_poly failed",
-            i
-        );
-        let sum = (fri_poly_id[i] + fri_poly_sibling[i]) / MyField::from(2);
-        let diff = (fri_poly_id[i] - fri_poly_sibling[i]) / (MyField::from(2) * fri_domain[id]);
-        assert_eq!(
-            sum + (diff * betas[i]),
-            fri_poly_id[i + 1],
-            "Evaluations of FRI poly at layer {} do not satisfy the recurrence relation",
-            i
+    fri_layers: &[FriLayerOpening],
+    fri_remainder: &DensePolynomial<MyFieldExt>,
+) -> Result<(), VerifierError> {
+    let mut layer_len = eval_domain_size;
+    let mut layer_offset = offset;
+    for (layer, opening) in fri_layers.iter().enumerate() {
+        let folding_factor = folding_factors[layer];
+        let num_leaves = layer_len / folding_factor;
+        let (leaf_id, _) = fri_layer_ids(id, layer_len, folding_factor);
+        // The points of a coset lie num_leaves positions apart, so they differ by the folding_factor-th roots of unity
+        let coset = coset(
+            coset_element(layer_offset, layer_len, leaf_id),
+            folding_factor,
         );
+        let next_value = match fri_layers.get(layer + 1) {
+            Some(next_opening) => {
+                fri_opened_value(id, num_leaves, folding_factors[layer + 1], next_opening)
+            }
+            None => fri_remainder.evaluate(&MyFieldExt::from_base_prime_field(
+                coset[0].pow([folding_factor as u64]),
+            )),
+        };
+        if fold_coset(&coset, &opening.values, betas[layer]) != next_value {
+            return Err(VerifierError::FriFoldingMismatch { layer, query });
+        }
 
-        layer_len /= 2;
-        fri_domain = compute_next_fri_domain(&fri_domain);
+        layer_len = num_leaves;
+        layer_offset = layer_offset.pow([folding_factor as u64]);
     }
+    Ok(())
 }
 
-// Checks the consistency of decomitted data with committed data
-pub fn check_decommittment_on_query(
-    eval_domain: &Vec<MyField>,
-    f_merkle_root: [u8; 32],
-    betas: &Vec<MyField>,
-    fri_polys_merkle_roots: &Vec<[u8; 32]>,
-    id: usize,
-    f_id: MyField,
-    authentication_path_f_id: &Vec<[u8; 32]>,
-    f_g_id: MyField,
-    authentication_path_f_g_id: &Vec<[u8; 32]>,
-    f_g2_id: MyField,
-    authentication_path_f_g2_id: &Vec<[u8; 32]>,
-    fri_poly_id: &Vec<MyField>,
-    authentication_path_fri_poly_id: &Vec<Vec<[u8; 32]>>,
-    fri_poly_sibling: &Vec<MyField>,
-    authentication_path_fri_poly_sibling: &Vec<Vec<[u8; 32]>>,
-) {
-    assert!(
-        verify_decommitment(id, f_id, authentication_path_f_id, f_merkle_root),
-        "check of decommitment of id in f failed"
-    );
-    assert!(
-        verify_decommitment(id + 8, f_g_id, authentication_path_f_g_id, f_merkle_root),
-        "check of decommitment of g*id in f failed"
-    );
-    assert!(
-        verify_decommitment(id + 16, f_g2_id, authentication_path_f_g2_id, f_merkle_root),
-        "check of decommitment of g^2*id in f failed"
-    );
-    check_decommittment_on_fri_layers(
-        eval_domain,
-        betas,
-        fri_polys_merkle_roots,
-        id,
-        fri_poly_id,
-        authentication_path_fri_poly_id,
-        fri_poly_sibling,
-        authentication_path_fri_poly_sibling,
-    );
+// Checks the consistency of the decommitted data of all the queries with the Merkle caps of the trace and of the FRI
+// layers, using their multi-proofs
+#[allow(clippy::too_many_arguments)]
+pub fn check_merkle_proofs<H: Hasher>(
+    eval_domain_size: usize,
+    folding_factors: &[usize],
+    trace_merkle_cap: &MerkleCap,
+    fri_polys_merkle_caps: &[MerkleCap],
+    row_offset: usize,
+    frame_size: usize,
+    queries: &[QueryOpening],
+    trace_proof: &MerkleProof,
+    fri_proofs: &[MerkleProof],
+) -> Result<(), VerifierError> {
+    let (trace_ids, trace_rows): (Vec<usize>, Vec<Vec<MyField>>) = queries
+        .iter()
+        .flat_map(|query| {
+            trace_frame_ids(query.id, row_offset, frame_size, eval_domain_size)
+                .into_iter()
+                .zip(query.trace_frame.iter().map(|opening| opening.row.clone()))
+        })
+        .unzip();
+    if !verify_multi_decommitment::<H>(
+        eval_domain_size,
+        &trace_ids,
+        &trace_rows,
+        &trace_proof.0,
+        &trace_merkle_cap.0,
+    ) {
+        return Err(VerifierError::TraceMerkleProofMismatch);
+    }
+    let ids: Vec<usize> = queries.iter().map(|query| query.id).collect();
+    let fri_layers: Vec<&[FriLayerOpening]> = queries
+        .iter()
+        .map(|query| query.fri_layers.as_slice())
+        .collect();
+    check_fri_merkle_proofs::<H>(
+        eval_domain_size,
+        folding_factors,
+        0,
+        fri_polys_merkle_caps,
+        &ids,
+        &fri_layers,
+        fri_proofs,
+    )
+}
+
+// Checks the consistency of the FRI layers opened at the given ids with the Merkle caps of the layers, using their
+// multi-proofs, the first ones being the ones of the layer first_layer
+pub fn check_fri_merkle_proofs<H: Hasher>(
+    eval_domain_size: usize,
+    folding_factors: &[usize],
+    first_layer: usize,
+    fri_polys_merkle_caps: &[MerkleCap],
+    ids: &[usize],
+    fri_layers: &[&[FriLayerOpening]],
+    fri_proofs: &[MerkleProof],
+) -> Result<(), VerifierError> {
+    let mut layer_len = eval_domain_size / folding_factors[..first_layer].iter().product::<usize>();
+    for (i, fri_proof) in fri_proofs.iter().enumerate() {
+        let layer = first_layer + i;
+        let folding_factor = folding_factors[layer];
+        let (layer_ids, layer_rows): (Vec<usize>, Vec<Vec<MyField>>) = ids
+            .iter()
+            .zip(fri_layers)
+            .map(|(&id, openings)| fri_leaf(id, layer_len, folding_factor, &openings[i]))
+            .unzip();
+        if !verify_multi_decommitment::<H>(
+            layer_len / folding_factor,
+            &layer_ids,
+            &layer_rows,
+            &fri_proof.0,
+            &fri_polys_merkle_caps[i].0,
+        ) {
+            return Err(VerifierError::MerkleProofMismatch { layer });
+        }
+        layer_len /= folding_factor;
+    }
+    Ok(())
+}
+
+// Checks the consistency of the openings of all the queries with the Merkle caps of the batched evaluation vectors
+// and of the committed folded FRI layers, using their multi-proofs
+#[allow(clippy::too_many_arguments)]
+pub fn check_batched_merkle_proofs<H: Hasher>(
+    eval_domain_size: usize,
+    folding_factors: &[usize],
+    batched_merkle_caps: &[MerkleCap],
+    fri_polys_merkle_caps: &[MerkleCap],
+    queries: &[FriQueryOpening],
+    batched_proofs: &[MerkleProof],
+    fri_proofs: &[MerkleProof],
+) -> Result<(), VerifierError> {
+    for (function, (cap, proof)) in batched_merkle_caps.iter().zip(batched_proofs).enumerate() {
+        let (leaf_ids, leaf_rows): (Vec<usize>, Vec<Vec<MyField>>) = queries
+            .iter()
+            .map(|query| {
+                fri_leaf(
+                    query.id,
+                    eval_domain_size,
+                    folding_factors[0],
+                    &query.batched[function],
+                )
+            })
+            .unzip();
+        if !verify_multi_decommitment::<H>(
+            eval_domain_size / folding_factors[0],
+            &leaf_ids,
+            &leaf_rows,
+            &proof.0,
+            &cap.0,
+        ) {
+            return Err(VerifierError::BatchedMerkleProofMismatch { function });
+        }
+    }
+    // The first FRI layer, the combination of the vectors, is not committed
+    let ids: Vec<usize> = queries.iter().map(|query| query.id).collect();
+    let fri_layers: Vec<&[FriLayerOpening]> = queries
+        .iter()
+        .map(|query| query.fri_layers.as_slice())
+        .collect();
+    check_fri_merkle_proofs::<H>(
+        eval_domain_size,
+        folding_factors,
+        1,
+        fri_polys_merkle_caps,
+        &ids,
+        &fri_layers,
+        fri_proofs,
+    )
+}
+
+// Opening of the first FRI layer at a query, combining the cosets of the batched evaluation vectors with their
+// coefficients
+pub fn batch_fri_openings(
+    openings: &[FriLayerOpening],
+    coefficients: &[MyFieldExt],
+) -> FriLayerOpening {
+    let values: Vec<&[MyFieldExt]> = openings
+        .iter()
+        .map(|opening| opening.values.as_slice())
+        .collect();
+    FriLayerOpening {
+        values: batch_fri_evaluations(&values, coefficients),
+    }
+}
+
+// Checks that the cap of each committed FRI layer from first_layer on holds 2^cap_height nodes, or all the leaves of
+// a smaller tree
+pub fn check_fri_caps_shape(
+    eval_domain_size: usize,
+    folding_factors: &[usize],
+    first_layer: usize,
+    cap_height: usize,
+    fri_polys_merkle_caps: &[MerkleCap],
+) -> Result<(), VerifierError> {
+    let mut layer_len = eval_domain_size / folding_factors[..first_layer].iter().product::<usize>();
+    for (i, cap) in fri_polys_merkle_caps.iter().enumerate() {
+        let layer = first_layer + i;
+        let num_leaves = (layer_len / fri_leaf_size(folding_factors, layer)).max(1);
+        let cap_len = merkle_cap_len(num_leaves, cap_height);
+        if cap.0.len() != cap_len {
+            return Err(VerifierError::MalformedProof(format!(
+                "Cap of FRI layer {} must contain {} nodes",
+                layer, cap_len
+            )));
+        }
+        layer_len /= folding_factors.get(layer).unwrap_or(&1);
+    }
+    Ok(())
+}
+
+// Checks that the remainder has degree less than the degree bound of the last FRI layer
+// The queries only check that the last layer is the evaluation of the remainder, so that nonzero coefficients beyond
+// the degree bound would let a function of any degree through, e.g. when the options make FRI fold nothing.
+pub fn check_fri_remainder_degree(
+    fri_remainder: &[MyFieldExt],
+    degree_bound: usize,
+) -> Result<(), VerifierError> {
+    if fri_remainder
+        .iter()
+        .skip(degree_bound)
+        .any(|coeff| *coeff != MyFieldExt::ZERO)
+    {
+        return Err(VerifierError::RemainderDegreeMismatch { degree_bound });
+    }
+    Ok(())
+}
+
+// Checks that a query opens every folded FRI layer from first_layer on, on a coset of the size of its folding factor
+pub fn check_fri_openings_shape(
+    folding_factors: &[usize],
+    first_layer: usize,
+    fri_layers: &[FriLayerOpening],
+) -> Result<(), VerifierError> {
+    if fri_layers.len() != folding_factors.len() - first_layer {
+        return Err(VerifierError::MalformedProof(format!(
+            "Queries must open {} FRI layers",
+            folding_factors.len() - first_layer
+        )));
+    }
+    for (i, opening) in fri_layers.iter().enumerate() {
+        let layer = first_layer + i;
+        if opening.values.len() != folding_factors[layer] {
+            return Err(VerifierError::MalformedProof(format!(
+                "Openings of FRI layer {} must contain {} elements",
+                layer, folding_factors[layer]
+            )));
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,206 @@
+use crate::common::{
+    finite_fields::{field_element_to_bytes, MyField},
+    merkle::{LEAF_PREFIX, NODE_PREFIX},
+    options::HashFunction,
+    utils::concat_slices,
+};
+use ark_ff::{AdditiveGroup, Field, PrimeField};
+use sha3::Digest;
+use std::sync::OnceLock;
+
+// Hash function with 32-byte digests used by the channel and the Merkle trees
+pub trait Hasher: Clone + Send + Sync + 'static {
+    // Hash function of the options selecting this hasher
+    const HASH_FUNCTION: HashFunction;
+
+    fn hash(data: &[u8]) -> [u8; 32];
+
+    // Hashes a leaf of a Merkle tree made of a row of field elements, the data being prefixed with LEAF_PREFIX
+    fn hash_leaf(row: &[MyField]) -> [u8; 32] {
+        let bytes: Vec<u8> = std::iter::once(LEAF_PREFIX)
+            .chain(row.iter().flat_map(field_element_to_bytes))
+            .collect();
+        Self::hash(&bytes)
+    }
+
+    // Hashes two nodes of a Merkle tree into their parent, the data being prefixed with NODE_PREFIX so that it never
+    // collides with the hash of a leaf
+    fn merge(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        Self::hash(&[&[NODE_PREFIX], left.as_slice(), right].concat())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256;
+
+impl Hasher for Sha256 {
+    const HASH_FUNCTION: HashFunction = HashFunction::Sha256;
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        sha2::Sha256::digest(data).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake3;
+
+impl Hasher for Blake3 {
+    const HASH_FUNCTION: HashFunction = HashFunction::Blake3;
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        blake3::hash(data).into()
+    }
+}
+
+// Keccak-256 as used by Ethereum, with the original Keccak padding rather than the SHA-3 one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keccak256;
+
+impl Hasher for Keccak256 {
+    const HASH_FUNCTION: HashFunction = HashFunction::Keccak256;
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        sha3::Keccak256::digest(data).into()
+    }
+}
+
+// Poseidon sponge over MyField, so that the commitments can be checked cheaply inside another STARK
+// A digest is made of 8 field elements encoded on 4 little-endian bytes each, as many as the capacity holds, so that
+// finding a collision takes about 2^126 permutations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Poseidon;
+
+const POSEIDON_WIDTH: usize = 24;
+const POSEIDON_RATE: usize = 16;
+const POSEIDON_DIGEST_SIZE: usize = 8;
+// Bytes of a digest element, MyField fitting in 32 bits
+const POSEIDON_DIGEST_ELEMENT_BYTES: usize = 4;
+const POSEIDON_FULL_ROUNDS: usize = 8;
+const POSEIDON_PARTIAL_ROUNDS: usize = 22;
+// x^5 is a permutation of MyField since gcd(5, p - 1) = 1
+const POSEIDON_ALPHA: u64 = 5;
+// Bytes packed into a field element, 3 bytes always being smaller than the modulus
+const POSEIDON_BYTES_PER_ELEMENT: usize = 3;
+
+struct PoseidonConstants {
+    round_constants: Vec<[MyField; POSEIDON_WIDTH]>,
+    mds: [[MyField; POSEIDON_WIDTH]; POSEIDON_WIDTH],
+}
+
+// Round constants are derived from SHA-256 of a fixed seed and the MDS matrix is the Cauchy matrix 1 / (i + WIDTH + j)
+fn poseidon_constants() -> &'static PoseidonConstants {
+    static CONSTANTS: OnceLock<PoseidonConstants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let num_rounds = POSEIDON_FULL_ROUNDS + POSEIDON_PARTIAL_ROUNDS;
+        let round_constants = (0..num_rounds)
+            .map(|round| {
+                std::array::from_fn(|i| {
+                    let seed = format!("stark101-poseidon-{}-{}", round, i);
+                    MyField::from_le_bytes_mod_order(&Sha256::hash(seed.as_bytes()))
+                })
+            })
+            .collect();
+        let mds = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                MyField::from((i + POSEIDON_WIDTH + j) as u64)
+                    .inverse()
+                    .unwrap()
+            })
+        });
+        PoseidonConstants {
+            round_constants,
+            mds,
+        }
+    })
+}
+
+fn poseidon_permutation(state: &mut [MyField; POSEIDON_WIDTH]) {
+    let constants = poseidon_constants();
+    let half_full_rounds = POSEIDON_FULL_ROUNDS / 2;
+    for (round, round_constants) in constants.round_constants.iter().enumerate() {
+        for (x, c) in state.iter_mut().zip(round_constants) {
+            *x += c;
+        }
+        let is_full_round =
+            round < half_full_rounds || round >= half_full_rounds + POSEIDON_PARTIAL_ROUNDS;
+        if is_full_round {
+            for x in state.iter_mut() {
+                *x = x.pow([POSEIDON_ALPHA]);
+            }
+        } else {
+            state[0] = state[0].pow([POSEIDON_ALPHA]);
+        }
+        *state = std::array::from_fn(|i| {
+            constants.mds[i]
+                .iter()
+                .zip(state.iter())
+                .map(|(m, x)| *m * x)
+                .sum()
+        });
+    }
+}
+
+fn poseidon_digest(state: &[MyField; POSEIDON_WIDTH]) -> [u8; 32] {
+    state[..POSEIDON_DIGEST_SIZE]
+        .iter()
+        .flat_map(|x| field_element_to_bytes(x)[..POSEIDON_DIGEST_ELEMENT_BYTES].to_vec())
+        .collect::<Vec<u8>>()
+        .try_into()
+        .unwrap()
+}
+
+impl Hasher for Poseidon {
+    const HASH_FUNCTION: HashFunction = HashFunction::Poseidon;
+
+    // The data is padded with a 1 byte and zeros, then absorbed POSEIDON_RATE elements at a time
+    fn hash(data: &[u8]) -> [u8; 32] {
+        let block_size = POSEIDON_RATE * POSEIDON_BYTES_PER_ELEMENT;
+        let mut padded = data.to_vec();
+        padded.push(1);
+        padded.resize(padded.len().next_multiple_of(block_size), 0);
+
+        let mut state = [MyField::ZERO; POSEIDON_WIDTH];
+        for block in padded.chunks(block_size) {
+            for (x, bytes) in state
+                .iter_mut()
+                .zip(block.chunks(POSEIDON_BYTES_PER_ELEMENT))
+            {
+                *x += MyField::from_le_bytes_mod_order(bytes);
+            }
+            poseidon_permutation(&mut state);
+        }
+        poseidon_digest(&state)
+    }
+
+    // The elements of the row are absorbed as they are, padded with a 1 and zeros, LEAF_PREFIX going into the capacity
+    fn hash_leaf(row: &[MyField]) -> [u8; 32] {
+        let mut padded = row.to_vec();
+        padded.push(MyField::ONE);
+        padded.resize(padded.len().next_multiple_of(POSEIDON_RATE), MyField::ZERO);
+
+        let mut state = [MyField::ZERO; POSEIDON_WIDTH];
+        state[POSEIDON_RATE] = MyField::from(LEAF_PREFIX);
+        for block in padded.chunks(POSEIDON_RATE) {
+            for (x, element) in state.iter_mut().zip(block) {
+                *x += element;
+            }
+            poseidon_permutation(&mut state);
+        }
+        poseidon_digest(&state)
+    }
+
+    // Both digests fill the rate, so that a node is hashed with a single permutation
+    // NODE_PREFIX goes into the capacity, which holds LEAF_PREFIX when absorbing a leaf.
+    fn merge(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut state = [MyField::ZERO; POSEIDON_WIDTH];
+        for (x, bytes) in state
+            .iter_mut()
+            .zip(concat_slices(left, right).chunks(POSEIDON_DIGEST_ELEMENT_BYTES))
+        {
+            *x = MyField::from_le_bytes_mod_order(bytes);
+        }
+        state[POSEIDON_RATE] = MyField::from(NODE_PREFIX);
+        poseidon_permutation(&mut state);
+        poseidon_digest(&state)
+    }
+}
@@ -1,39 +1,230 @@
-use crate::common::{finite_fields::MyField, utils::concat_slices};
-use ark_ff::{BigInteger, PrimeField};
-use rs_merkle::algorithms::Sha256;
-use rs_merkle::{Hasher, MerkleTree};
+use crate::common::{finite_fields::MyField, hasher::Hasher};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+// Prefixes of the data hashed for a leaf and for an internal node, so that the bytes of two children can never be
+// passed off as a leaf, nor a leaf as the children of a node
+pub const LEAF_PREFIX: u8 = 0x00;
+pub const NODE_PREFIX: u8 = 0x01;
+
+// Merkle tree over a power-of-two number of leaves, stored as a flat array in which the children of node i are the
+// nodes 2i and 2i + 1: the root is node 1, the nodes at depth d are nodes 2^d..2^(d+1) and the leaves come last
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H: Hasher> {
+    nodes: Vec<[u8; 32]>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    // Builds the tree in place, hashing the leaves then each layer of nodes in parallel
+    fn build(num_leaves: usize, hash_leaf: impl Fn(usize) -> [u8; 32] + Sync) -> Self {
+        assert!(
+            num_leaves.is_power_of_two(),
+            "Number of leaves must be a power of two"
+        );
+        let mut nodes = vec![[0u8; 32]; 2 * num_leaves];
+        nodes[num_leaves..]
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, node)| *node = hash_leaf(i));
+        let mut layer_len = num_leaves;
+        while layer_len > 1 {
+            let (parents, children) = nodes.split_at_mut(layer_len);
+            parents[layer_len / 2..]
+                .par_iter_mut()
+                .zip(children[..layer_len].par_chunks(2))
+                .for_each(|(parent, pair)| *parent = H::merge(&pair[0], &pair[1]));
+            layer_len /= 2;
+        }
+        Self {
+            nodes,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn from_leaves(leaves: &[[u8; 32]]) -> Self {
+        Self::build(leaves.len(), |i| leaves[i])
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes[1]
+    }
+
+    pub fn leaves_len(&self) -> usize {
+        self.nodes.len() / 2
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        self.layer(self.leaves_len().trailing_zeros() as usize)
+    }
+
+    // Nodes at a depth, from left to right
+    pub fn layer(&self, depth: usize) -> &[[u8; 32]] {
+        &self.nodes[1 << depth..2 << depth]
+    }
+
+    // Siblings of the nodes on the way from a leaf up to the root, borrowed from the tree
+    pub fn authentication_path(&self, leaf_id: usize) -> impl Iterator<Item = &[u8; 32]> {
+        let mut node = self.leaves_len() + leaf_id;
+        std::iter::from_fn(move || {
+            (node > 1).then(|| {
+                let sibling = &self.nodes[node ^ 1];
+                node /= 2;
+                sibling
+            })
+        })
+    }
+}
+
+// Hashes a leaf made of a row of field elements, see Hasher::hash_leaf
+pub fn hash_leaf<H: Hasher>(row: &[MyField]) -> [u8; 32] {
+    H::hash_leaf(row)
+}
 
 // Creates Merkle Tree using given data (elements of MyField)
-pub fn create_merkle_tree(data: &Vec<MyField>) -> MerkleTree<Sha256> {
-    let leaves: Vec<[u8; 32]> = data
-        .iter()
-        .map(|eval| Sha256::hash(&eval.into_bigint().to_bytes_le()))
-        .collect();
-    MerkleTree::<Sha256>::from_leaves(&leaves)
+pub fn create_merkle_tree<H: Hasher>(data: &[MyField]) -> MerkleTree<H> {
+    MerkleTree::build(data.len(), |i| {
+        hash_leaf::<H>(std::slice::from_ref(&data[i]))
+    })
+}
+
+// Creates Merkle Tree over several columns of the same length, the i-th leaf committing to the i-th element of
+// every column
+pub fn create_merkle_tree_from_columns<H: Hasher>(columns: &[Vec<MyField>]) -> MerkleTree<H> {
+    MerkleTree::build(columns[0].len(), |i| {
+        hash_leaf::<H>(&columns.iter().map(|column| column[i]).collect::<Vec<_>>())
+    })
 }
 
 // Gets authentication path of an index
-pub fn get_authentication_path(merkle: &MerkleTree<Sha256>, leaf_id: usize) -> Vec<[u8; 32]> {
-    merkle.proof(&[leaf_id]).proof_hashes().to_vec()
+pub fn get_authentication_path<H: Hasher>(merkle: &MerkleTree<H>, leaf_id: usize) -> Vec<[u8; 32]> {
+    merkle.authentication_path(leaf_id).copied().collect()
+}
+
+// Number of nodes of the Merkle cap of height cap_height of a tree with num_leaves leaves
+pub fn merkle_cap_len(num_leaves: usize, cap_height: usize) -> usize {
+    (1 << cap_height).min(num_leaves)
+}
+
+// Gets the Merkle cap of height cap_height: the nodes at depth cap_height, or the leaves when the tree is not as deep
+// A cap of height 0 is the root.
+pub fn get_merkle_cap<H: Hasher>(merkle: &MerkleTree<H>, cap_height: usize) -> Vec<[u8; 32]> {
+    let cap_len = merkle_cap_len(merkle.leaves_len(), cap_height);
+    merkle.layer(cap_len.trailing_zeros() as usize).to_vec()
+}
+
+// Gets the authentication nodes of a multi-proof of a set of leaves against the Merkle cap of height cap_height: layer
+// by layer from the leaves up to the cap, the siblings of the nodes computable from the leaves which are not computable
+// themselves, in increasing order of position
+pub fn get_multi_proof<H: Hasher>(
+    merkle: &MerkleTree<H>,
+    leaf_ids: &[usize],
+    cap_height: usize,
+) -> Vec<[u8; 32]> {
+    let mut layer_ids = leaf_ids.to_vec();
+    layer_ids.sort_unstable();
+    layer_ids.dedup();
+    let cap_len = merkle_cap_len(merkle.leaves_len(), cap_height);
+    let mut layer_len = merkle.leaves_len();
+    let mut depth = layer_len.trailing_zeros() as usize;
+    let mut nodes = vec![];
+    while layer_len > cap_len {
+        let layer = merkle.layer(depth);
+        for &id in &layer_ids {
+            if layer_ids.binary_search(&(id ^ 1)).is_err() {
+                nodes.push(layer[id ^ 1]);
+            }
+        }
+        layer_ids = layer_ids.iter().map(|id| id / 2).collect();
+        layer_ids.dedup();
+        layer_len /= 2;
+        depth -= 1;
+    }
+    nodes
+}
+
+// Verifies that the decommitment of a set of rows of a Merkle Tree with num_leaves leaves matches with the
+// authentication nodes of a multi-proof and the Merkle cap of the tree
+// num_leaves must be a power of two, as for all the trees of the protocol. A leaf may be opened several times, in
+// which case all its rows must be equal.
+pub fn verify_multi_decommitment<H: Hasher>(
+    num_leaves: usize,
+    leaf_ids: &[usize],
+    rows: &[Vec<MyField>],
+    nodes: &[[u8; 32]],
+    cap: &[[u8; 32]],
+) -> bool {
+    if !cap.len().is_power_of_two() || cap.len() > num_leaves {
+        return false;
+    }
+    let mut layer = BTreeMap::new();
+    for (&leaf_id, row) in leaf_ids.iter().zip(rows) {
+        let hash = hash_leaf::<H>(row);
+        if leaf_id >= num_leaves
+            || layer
+                .insert(leaf_id, hash)
+                .is_some_and(|other| other != hash)
+        {
+            return false;
+        }
+    }
+    let mut nodes = nodes.iter();
+    let mut layer_len = num_leaves;
+    while layer_len > cap.len() {
+        let mut next_layer = BTreeMap::new();
+        let mut known = layer.into_iter().peekable();
+        while let Some((id, hash)) = known.next() {
+            // The left sibling of a right node would have been consumed with it
+            let sibling =
+                match known.next_if(|&(sibling_id, _)| id & 1 == 0 && sibling_id == id + 1) {
+                    Some((_, sibling_hash)) => sibling_hash,
+                    None => match nodes.next() {
+                        Some(node) => *node,
+                        None => return false,
+                    },
+                };
+            let parent = if id & 1 == 0 {
+                H::merge(&hash, &sibling)
+            } else {
+                H::merge(&sibling, &hash)
+            };
+            next_layer.insert(id / 2, parent);
+        }
+        layer = next_layer;
+        layer_len /= 2;
+    }
+    nodes.next().is_none() && !layer.is_empty() && layer.iter().all(|(&id, hash)| cap[id] == *hash)
 }
 
 // Verifies that a decommitment matches with authentication path included in a Merkle proof
-pub fn verify_decommitment(
+pub fn verify_decommitment<H: Hasher>(
     leaf_id: usize,
     leaf_data: MyField,
-    authentication_path: &Vec<[u8; 32]>,
+    authentication_path: &[[u8; 32]],
+    root: [u8; 32],
+) -> bool {
+    verify_row_decommitment::<H>(leaf_id, &[leaf_data], authentication_path, root)
+}
+
+// Verifies that the decommitment of a row of a Merkle Tree over several columns matches with authentication path
+// included in a Merkle proof
+pub fn verify_row_decommitment<H: Hasher>(
+    leaf_id: usize,
+    row: &[MyField],
+    authentication_path: &[[u8; 32]],
     root: [u8; 32],
 ) -> bool {
     let mut leaf_id = leaf_id;
-    let content_hash = Sha256::hash(&leaf_data.into_bigint().to_bytes_le());
+    let content_hash = hash_leaf::<H>(row);
     let mut hash = content_hash;
-    for i in 0..authentication_path.len() {
+    for sibling_hash in authentication_path {
         if leaf_id & 1 == 1 {
-            hash = Sha256::hash(&concat_slices(&authentication_path[i], &hash).as_slice());
+            hash = H::merge(sibling_hash, &hash);
         } else {
-            hash = Sha256::hash(&concat_slices(&hash, &authentication_path[i]).as_slice());
+            hash = H::merge(&hash, sibling_hash);
         }
-        leaf_id = leaf_id >> 1;
+        leaf_id >>= 1;
     }
     hash == root
 }
@@ -1,6 +1,10 @@
 pub mod channel;
+pub mod error;
 pub mod finite_fields;
 pub mod fri;
+pub mod hasher;
 pub mod merkle;
+pub mod options;
 pub mod polynomials;
+pub mod proof;
 pub mod utils;
@@ -0,0 +1,186 @@
+use crate::air::Air;
+use crate::common::{finite_fields::MyField, fri::fri_folding_factors};
+use ark_ff::FftField;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Hash function used by the channel and the Merkle trees
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashFunction {
+    Sha256,
+    Blake3,
+    Keccak256,
+    Poseidon,
+}
+
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashFunction::Sha256),
+            "blake3" => Ok(HashFunction::Blake3),
+            "keccak256" => Ok(HashFunction::Keccak256),
+            "poseidon" => Ok(HashFunction::Poseidon),
+            _ => Err(format!("unknown hash function {}", s)),
+        }
+    }
+}
+
+// Parameters shared by the prover and the verifier
+// blowup: ratio between the size of the evaluation domain and the degree bound of the committed polynomials
+// num_queries: number of queries decommitted at the end of the protocol
+// folding_factor: ratio between the sizes of consecutive FRI layers
+// grinding_bits: number of leading zero bits of the proof-of-work required before drawing the queries
+// hash: hash function of the commitments and of the Fiat-Shamir transform
+// cap_height: depth of the nodes committed in place of the root of each Merkle tree (0 for the root)
+// max_remainder_degree: degree at which FRI stops folding, the remainder polynomial being sent in the clear
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofOptions {
+    pub blowup: usize,
+    pub num_queries: usize,
+    pub folding_factor: usize,
+    pub grinding_bits: u32,
+    pub hash: HashFunction,
+    pub cap_height: usize,
+    pub max_remainder_degree: usize,
+}
+
+impl Default for ProofOptions {
+    fn default() -> Self {
+        Self::new(8, 3, 2, 0, HashFunction::Sha256, 0, 0)
+    }
+}
+
+impl ProofOptions {
+    pub fn new(
+        blowup: usize,
+        num_queries: usize,
+        folding_factor: usize,
+        grinding_bits: u32,
+        hash: HashFunction,
+        cap_height: usize,
+        max_remainder_degree: usize,
+    ) -> Self {
+        Self::try_new(
+            blowup,
+            num_queries,
+            folding_factor,
+            grinding_bits,
+            hash,
+            cap_height,
+            max_remainder_degree,
+        )
+        .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Same as new, but returns the reason for which the options are invalid instead of panicking
+    pub fn try_new(
+        blowup: usize,
+        num_queries: usize,
+        folding_factor: usize,
+        grinding_bits: u32,
+        hash: HashFunction,
+        cap_height: usize,
+        max_remainder_degree: usize,
+    ) -> Result<Self, String> {
+        if blowup < 2 || !blowup.is_power_of_two() {
+            return Err("Blowup factor must be a power of two greater than 1".to_string());
+        }
+        if num_queries == 0 {
+            return Err("Number of queries must be positive".to_string());
+        }
+        if !matches!(folding_factor, 2 | 4 | 8 | 16) {
+            return Err("Folding factor must be 2, 4, 8 or 16".to_string());
+        }
+        if grinding_bits > 32 {
+            return Err("Grinding bits must be at most 32".to_string());
+        }
+        if cap_height > 16 {
+            return Err("Cap height must be at most 16".to_string());
+        }
+        if !max_remainder_degree
+            .checked_add(1)
+            .is_some_and(usize::is_power_of_two)
+        {
+            return Err("Maximal remainder degree must be a power of two minus one".to_string());
+        }
+        Ok(Self {
+            blowup,
+            num_queries,
+            folding_factor,
+            grinding_bits,
+            hash,
+            cap_height,
+            max_remainder_degree,
+        })
+    }
+
+    // Checks the options against the sizes of an AIR: the eval domain must be a subgroup coset of the field, the queries
+    // must be distinct points of the eval domain, and the remainder must not have more coefficients than CP
+    pub fn check<A: Air>(&self, air: &A) -> Result<(), String> {
+        let max_eval_domain_size = 1_usize << MyField::TWO_ADICITY;
+        if self.blowup
+            > max_eval_domain_size / air.trace_domain_size().max(air.composition_degree_bound())
+        {
+            return Err(format!(
+                "Eval domain size must not exceed 2^{}, the largest subgroup of the field",
+                MyField::TWO_ADICITY
+            ));
+        }
+        let eval_domain_size = self.eval_domain_size(air);
+        if self.num_queries > eval_domain_size {
+            return Err(format!(
+                "Number of queries must not exceed the eval domain size {}",
+                eval_domain_size
+            ));
+        }
+        let composition_degree_bound = air.composition_degree_bound();
+        if self.max_remainder_degree >= composition_degree_bound {
+            return Err(format!(
+                "Maximal remainder degree must be less than the degree bound of the composition polynomial {}",
+                composition_degree_bound
+            ));
+        }
+        Ok(())
+    }
+
+    // Serializes the options so that they can be committed into the transcript
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let hash_id: u8 = match self.hash {
+            HashFunction::Sha256 => 0,
+            HashFunction::Blake3 => 1,
+            HashFunction::Keccak256 => 2,
+            HashFunction::Poseidon => 3,
+        };
+        [
+            (self.blowup as u64).to_le_bytes().as_slice(),
+            &(self.num_queries as u64).to_le_bytes(),
+            &(self.folding_factor as u64).to_le_bytes(),
+            &self.grinding_bits.to_le_bytes(),
+            &[hash_id],
+            &(self.cap_height as u64).to_le_bytes(),
+            &(self.max_remainder_degree as u64).to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    // Size of the coset on which the trace and composition polynomials are evaluated
+    pub fn eval_domain_size<A: Air>(&self, air: &A) -> usize {
+        self.blowup * air.trace_domain_size().max(air.composition_degree_bound())
+    }
+
+    // Folding factors of the FRI layers folding the composition polynomial down to the remainder polynomial
+    pub fn fri_folding_factors<A: Air>(&self, air: &A) -> Vec<usize> {
+        fri_folding_factors(
+            air.composition_degree_bound(),
+            self.folding_factor,
+            self.max_remainder_degree + 1,
+        )
+    }
+
+    // Number of FRI layers folding the composition polynomial down to the remainder polynomial
+    pub fn num_fri_layers<A: Air>(&self, air: &A) -> usize {
+        self.fri_folding_factors(air).len()
+    }
+}
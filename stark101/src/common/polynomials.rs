@@ -1,63 +1,203 @@
-use crate::common::finite_fields::MyField;
-use ark_ff::Field;
+use crate::common::finite_fields::{MyField, MyFieldExt};
+use ark_ff::{batch_inversion, AdditiveGroup, FftField, Field};
 use ark_poly::polynomial::univariate::*;
 use ark_poly::DenseUVPolynomial;
-use ark_std::{rand::Rng, Zero};
+use ark_std::rand::Rng;
 use rayon::prelude::*;
+use std::iter::{successors, zip};
 
 /// Generates a random polynomial of certain degree
 pub fn random_polynomial(rng: &mut impl Rng, degree: usize) -> DensePolynomial<MyField> {
     DensePolynomial::<MyField>::rand(degree, rng)
 }
 
-/// Calculates lagrange polynomials corresponding to given points
-fn calculate_lagrange_polynomials(x_points: &Vec<MyField>) -> Vec<DensePolynomial<MyField>> {
-    let n = x_points.len();
-
-    // Computes monomials and their product
-    let monomials = x_points.par_iter().map(|x| DensePolynomial {
-        coeffs: vec![-*x, MyField::from(1)],
-    });
-    let product = monomials.clone().into_par_iter().reduce(
-        || DensePolynomial {
-            coeffs: vec![MyField::ONE],
-        },
-        |product, monomial| product * monomial,
+/// Returns the generator of the multiplicative subgroup of a given power of two size
+fn subgroup_generator(size: usize) -> MyField {
+    assert!(
+        size.is_power_of_two() && size.trailing_zeros() <= MyField::TWO_ADICITY,
+        "There is no subgroup of size {}",
+        size
     );
+    MyField::GENERATOR.pow([3221225472_u64 / size as u64])
+}
 
-    // Computes Lagrange polynomials
-    let lagrange_polys = (0..n)
-        .into_par_iter()
-        .map(|i| {
-            let numerator = &product
-                / DensePolynomial {
-                    coeffs: vec![-x_points[i], MyField::from(1)],
-                };
-            let denominator = (0..n)
-                .filter(|&j| j != i)
-                .collect::<Vec<_>>() // Collect into Vec to enable parallel iteration
-                .into_par_iter()
-                .map(|j| x_points[i] - x_points[j])
-                .reduce(|| MyField::ONE, |val, denominator| denominator * val);
-            &numerator * denominator.inverse().unwrap()
+/// Returns the points offset, offset * h, offset * h^2, .. of the coset offset * H, H being the subgroup of a given
+/// power of two size generated by h
+pub fn coset(offset: MyField, size: usize) -> Vec<MyField> {
+    let h = subgroup_generator(size);
+    successors(Some(offset), |point| Some(*point * h))
+        .take(size)
+        .collect()
+}
+
+/// Returns the i-th point offset * h^i of the coset offset * H, H being the subgroup of a given power of two size
+/// generated by h
+pub fn coset_element(offset: MyField, size: usize, i: usize) -> MyField {
+    offset * subgroup_generator(size).pow([i as u64])
+}
+
+/// Replaces coefficients by the evaluations of their polynomial on the subgroup generated by root, whose order is
+/// the number of coefficients (iterative radix-2 Cooley-Tukey NTT)
+fn ntt_in_place(values: &mut [MyField], root: MyField) {
+    let n = values.len();
+    if n <= 1 {
+        return;
+    }
+    let log_n = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - log_n);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let root_len = root.pow([(n / len) as u64]);
+        let twiddles: Vec<MyField> = successors(Some(MyField::ONE), |w| Some(*w * root_len))
+            .take(len / 2)
+            .collect();
+        values.par_chunks_mut(len).for_each(|chunk| {
+            let (low, high) = chunk.split_at_mut(len / 2);
+            for ((a, b), w) in zip(zip(low, high), &twiddles) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+            }
+        });
+        len *= 2;
+    }
+}
+
+/// Evaluates a polynomial on the coset offset * H, H being the subgroup of a given power of two size, using the NTT
+pub fn evaluate_on_coset(
+    poly: &DensePolynomial<MyField>,
+    offset: MyField,
+    size: usize,
+) -> Vec<MyField> {
+    assert!(
+        poly.coeffs.len() <= size,
+        "The degree of the polynomial must be less than {}",
+        size
+    );
+    let mut values = vec![MyField::ZERO; size];
+    let offset_powers = successors(Some(MyField::ONE), |power| Some(*power * offset));
+    for ((value, coeff), offset_power) in zip(zip(&mut values, &poly.coeffs), offset_powers) {
+        *value = *coeff * offset_power;
+    }
+    ntt_in_place(&mut values, subgroup_generator(size));
+    values
+}
+
+/// Returns the values at y, y * g, .. y * g^(num_points - 1) of the polynomial Z vanishing on the last n - len points
+/// g^len, .. g^(n - 1) of the subgroup of size n generated by g
+/// Z(y * g^i) is g^(i * (n - len)) times the product of the y - g^k over the n - len consecutive k from len - i, so
+/// all the values follow from prefix products of the y - g^k, which must not vanish.
+pub fn missing_points_vanishing_values(
+    y: MyField,
+    len: usize,
+    n: usize,
+    num_points: usize,
+) -> Vec<MyField> {
+    let g = subgroup_generator(n);
+    let m = n - len;
+    // The prefix products run over k from len + 1 - num_points to n - 1, g^k being taken modulo n
+    let first_power = g.pow([(n + len + 1 - num_points) as u64]);
+    let mut prefix_products = vec![MyField::ONE];
+    for g_k in successors(Some(first_power), |g_k| Some(*g_k * g)).take(m + num_points - 1) {
+        prefix_products.push(*prefix_products.last().unwrap() * (y - g_k));
+    }
+    let mut denominators = prefix_products[..num_points].to_vec();
+    batch_inversion(&mut denominators);
+    let g_m = g.pow([m as u64]);
+    successors(Some(MyField::ONE), |power| Some(*power * g_m))
+        .take(num_points)
+        .enumerate()
+        .map(|(i, g_im)| {
+            let j = num_points - 1 - i;
+            g_im * prefix_products[j + m] * denominators[j]
         })
-        .collect();
+        .collect()
+}
+
+/// Interpolates the polynomial of degree less than evaluations.len() taking the given values on the first points
+/// 1, g, g^2, .. of the subgroup generated by g, whose size is evaluations.len() rounded up to a power of two.
+/// When evaluations.len() is a power of two this is a single inverse NTT. Otherwise, with Z the polynomial vanishing
+/// on the points that are not given, the product of the interpolant with Z has degree less than the size of the
+/// subgroup: it is interpolated from its values on the subgroup, then divided by Z pointwise on a coset, in O(n log n).
+pub fn interpolate_on_subgroup(evaluations: &[MyField]) -> DensePolynomial<MyField> {
+    let len = evaluations.len();
+    let n = len.next_power_of_two();
+    if len == n {
+        let mut coeffs = evaluations.to_vec();
+        ntt_in_place(&mut coeffs, subgroup_generator(n).inverse().unwrap());
+        let n_inv = MyField::from(n as u64).inverse().unwrap();
+        coeffs.par_iter_mut().for_each(|coeff| *coeff *= n_inv);
+        return DensePolynomial::from_coefficients_vec(coeffs);
+    }
 
-    lagrange_polys
+    let mut values: Vec<MyField> = zip(
+        evaluations,
+        missing_points_vanishing_values(MyField::ONE, len, n, len),
+    )
+    .map(|(evaluation, z)| *evaluation * z)
+    .collect();
+    values.resize(n, MyField::ZERO);
+    let product = interpolate_on_subgroup(&values);
+    let w = MyField::GENERATOR;
+    let mut z_inv = missing_points_vanishing_values(w, len, n, n);
+    batch_inversion(&mut z_inv);
+    let quotient: Vec<MyField> = zip(evaluate_on_coset(&product, w, n), z_inv)
+        .map(|(value, z_inv)| value * z_inv)
+        .collect();
+    interpolate_on_coset(w, &quotient)
 }
 
-/// Interpolates a polynomial from given evaluations at points using Lagrange interpolation.
-pub fn interpolate_polynomial(
-    x_points: &Vec<MyField>,
-    y_points: &Vec<MyField>,
-) -> DensePolynomial<MyField> {
-    let n = x_points.len();
-    let lagrange_polys = calculate_lagrange_polynomials(&x_points);
+/// Interpolates the polynomial of degree less than evaluations.len() taking the given values on the first points
+/// offset, offset * g, offset * g^2, .. of a coset of the subgroup generated by g (see interpolate_on_subgroup)
+pub fn interpolate_on_coset(offset: MyField, evaluations: &[MyField]) -> DensePolynomial<MyField> {
+    // p(offset * x) is interpolated on the subgroup, then its coefficients are divided by the powers of offset
+    let mut poly = interpolate_on_subgroup(evaluations);
+    let offset_inv = offset.inverse().unwrap();
+    let offset_inv_powers = successors(Some(MyField::ONE), |power| Some(*power * offset_inv));
+    for (coeff, offset_inv_power) in zip(&mut poly.coeffs, offset_inv_powers) {
+        *coeff *= offset_inv_power;
+    }
+    poly
+}
 
-    (0..n)
+/// Interpolates a polynomial of the extension field on a coset (see interpolate_on_coset), each coefficient of the
+/// evaluations being interpolated in the base field
+pub fn interpolate_extension_on_coset(
+    offset: MyField,
+    evaluations: &[MyFieldExt],
+) -> DensePolynomial<MyFieldExt> {
+    let coefficient_polys: Vec<DensePolynomial<MyField>> = (0..MyFieldExt::extension_degree()
+        as usize)
         .into_par_iter()
-        .map(|i| &lagrange_polys[i] * y_points[i])
-        .reduce(|| DensePolynomial::zero(), |result, value| result + &value)
+        .map(|k| {
+            let coefficient_evaluations: Vec<MyField> = evaluations
+                .iter()
+                .map(|evaluation| evaluation.to_base_prime_field_elements().nth(k).unwrap())
+                .collect();
+            interpolate_on_coset(offset, &coefficient_evaluations)
+        })
+        .collect();
+    let len = coefficient_polys
+        .iter()
+        .map(|poly| poly.coeffs.len())
+        .max()
+        .unwrap();
+    let coeffs = (0..len)
+        .map(|i| {
+            MyFieldExt::from_base_prime_field_elems(
+                coefficient_polys
+                    .iter()
+                    .map(|poly| poly.coeffs.get(i).copied().unwrap_or(MyField::ZERO)),
+            )
+            .unwrap()
+        })
+        .collect();
+    DensePolynomial::from_coefficients_vec(coeffs)
 }
 
 /// Raises a polynomial to a power
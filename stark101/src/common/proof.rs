@@ -0,0 +1,287 @@
+use crate::common::{
+    error::VerifierError,
+    finite_fields::{
+        field_element_to_bytes, flatten_extension_field_elements, MyField, MyFieldExt,
+    },
+    options::ProofOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Authentication nodes of a multi-proof of the leaves opened in a Merkle tree, see merkle::get_multi_proof
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof(#[serde(with = "hex_hashes")] pub Vec<[u8; 32]>);
+
+impl MerkleProof {
+    // Concatenation of the hashes, as sent on the channel
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.concat()
+    }
+}
+
+// Nodes committing to a Merkle tree at the cap height of the options, see merkle::get_merkle_cap
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MerkleCap(#[serde(with = "hex_hashes")] pub Vec<[u8; 32]>);
+
+impl MerkleCap {
+    // Concatenation of the hashes, as sent on the channel
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.concat()
+    }
+}
+
+// Row of evaluations of the trace polynomials at a point of the eval domain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TraceOpening {
+    #[serde(with = "hex_field_elements")]
+    pub row: Vec<MyField>,
+}
+
+// Elements of a FRI layer on the coset of a query that fold into one element of the next layer, in the order of their
+// leaf, FRI layers being in the extension field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FriLayerOpening {
+    #[serde(with = "hex_extension_field_elements")]
+    pub values: Vec<MyFieldExt>,
+}
+
+// Decommitment on a query: the frame of trace rows at x, gx, g^2x, .. and the FRI layers at x, x^k, x^(k^2), .., k
+// being the folding factor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QueryOpening {
+    pub id: usize,
+    pub trace_frame: Vec<TraceOpening>,
+    pub fri_layers: Vec<FriLayerOpening>,
+}
+
+// STARK proof produced by the prover and consumed by the verifier
+// The random challenges are not part of the proof: the verifier derives them from the commitments. Each tree is
+// committed by its Merkle cap and the openings of all the queries are authenticated by a single multi-proof per tree,
+// up to the cap: one for the trace and one per opened FRI layer. The last FRI layer is not committed: the coefficients
+// of its polynomial, the remainder, are sent instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StarkProof {
+    pub options: ProofOptions,
+    pub trace_cap: MerkleCap,
+    pub CP_cap: MerkleCap,
+    pub fri_caps: Vec<MerkleCap>,
+    #[serde(with = "hex_extension_field_elements")]
+    pub fri_remainder: Vec<MyFieldExt>,
+    pub pow_nonce: u64,
+    pub queries: Vec<QueryOpening>,
+    pub trace_proof: MerkleProof,
+    pub fri_proofs: Vec<MerkleProof>,
+}
+
+// Decommitment of a standalone FRI proof on a query: the coset at x of each batched function, which combine into the
+// first FRI layer, then the folded FRI layers at x^k, x^(k^2), .., k being the folding factor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FriQueryOpening {
+    pub id: usize,
+    pub batched: Vec<FriLayerOpening>,
+    pub fri_layers: Vec<FriLayerOpening>,
+}
+
+// Proof of the standalone FRI low-degree test of a batch of functions, see prover::fri and verifier::fri
+// Each function is committed by its own cap and authenticated by its own multi-proof. The first FRI layer, their
+// random linear combination, is not committed, nor is the last one, whose remainder is sent instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FriProof {
+    pub batched_caps: Vec<MerkleCap>,
+    pub fri_caps: Vec<MerkleCap>,
+    #[serde(with = "hex_extension_field_elements")]
+    pub fri_remainder: Vec<MyFieldExt>,
+    pub pow_nonce: u64,
+    pub queries: Vec<FriQueryOpening>,
+    pub batched_proofs: Vec<MerkleProof>,
+    pub fri_proofs: Vec<MerkleProof>,
+}
+
+// Encodings of a serialized proof: human-readable JSON or compact binary (bincode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofFormat {
+    Json,
+    Binary,
+}
+
+impl FromStr for ProofFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ProofFormat::Json),
+            "binary" => Ok(ProofFormat::Binary),
+            _ => Err(format!(
+                "unknown proof format {} (expected json or binary)",
+                s
+            )),
+        }
+    }
+}
+
+pub fn serialize_proof(proof: &StarkProof, format: ProofFormat) -> Vec<u8> {
+    match format {
+        ProofFormat::Json => serde_json::to_vec_pretty(proof).expect("Failed to serialize proof"),
+        ProofFormat::Binary => bincode::serialize(proof).expect("Failed to serialize proof"),
+    }
+}
+
+pub fn deserialize_proof(bytes: &[u8], format: ProofFormat) -> Result<StarkProof, VerifierError> {
+    match format {
+        ProofFormat::Json => serde_json::from_slice(bytes)
+            .map_err(|error| VerifierError::MalformedProof(error.to_string())),
+        ProofFormat::Binary => bincode::deserialize(bytes)
+            .map_err(|error| VerifierError::MalformedProof(error.to_string())),
+    }
+}
+
+// Concatenation of the canonical encodings of field elements, as sent on the channel
+pub fn field_elements_to_bytes(elements: &[MyField]) -> Vec<u8> {
+    elements.iter().flat_map(field_element_to_bytes).collect()
+}
+
+// Concatenation of the coefficients of elements of the extension field, as sent on the channel
+pub fn extension_field_elements_to_bytes(elements: &[MyFieldExt]) -> Vec<u8> {
+    field_elements_to_bytes(&flatten_extension_field_elements(elements))
+}
+
+// Hashes and field elements are serialized as hex strings, field elements being rejected unless canonical
+mod hex_hash {
+    use hex::{decode, encode};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let bytes = decode(String::deserialize(deserializer)?).map_err(Error::custom)?;
+        bytes
+            .try_into()
+            .map_err(|_| Error::custom("Hash must have exactly 32 bytes"))
+    }
+}
+
+mod hex_hashes {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Hash(#[serde(with = "super::hex_hash")] [u8; 32]);
+
+    pub fn serialize<S: Serializer>(hashes: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(hashes.iter().map(|hash| Hash(*hash)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; 32]>, D::Error> {
+        let hashes = Vec::<Hash>::deserialize(deserializer)?;
+        Ok(hashes.into_iter().map(|hash| hash.0).collect())
+    }
+}
+
+mod hex_field_element {
+    use crate::common::finite_fields::{
+        field_element_from_bytes, field_element_to_bytes, MyField, FIELD_ELEMENT_BYTES,
+    };
+    use hex::{decode, encode};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(element: &MyField, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(field_element_to_bytes(element)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MyField, D::Error> {
+        let bytes: [u8; FIELD_ELEMENT_BYTES] = decode(String::deserialize(deserializer)?)
+            .map_err(Error::custom)?
+            .try_into()
+            .map_err(|_| Error::custom("Field element must have exactly 8 bytes"))?;
+        field_element_from_bytes(bytes)
+            .ok_or_else(|| Error::custom("Field element must be smaller than the modulus"))
+    }
+}
+
+mod hex_field_elements {
+    use crate::common::finite_fields::MyField;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct FieldElement(#[serde(with = "super::hex_field_element")] MyField);
+
+    pub fn serialize<S: Serializer>(
+        elements: &[MyField],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(elements.iter().map(|element| FieldElement(*element)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<MyField>, D::Error> {
+        let elements = Vec::<FieldElement>::deserialize(deserializer)?;
+        Ok(elements.into_iter().map(|element| element.0).collect())
+    }
+}
+
+mod hex_extension_field_element {
+    use crate::common::finite_fields::{
+        field_element_from_bytes, MyField, MyFieldExt, FIELD_ELEMENT_BYTES,
+    };
+    use ark_ff::Field;
+    use hex::{decode, encode};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        element: &MyFieldExt,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(super::extension_field_elements_to_bytes(&[
+            *element,
+        ])))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MyFieldExt, D::Error> {
+        let bytes = decode(String::deserialize(deserializer)?).map_err(Error::custom)?;
+        let element_size = FIELD_ELEMENT_BYTES * MyFieldExt::extension_degree() as usize;
+        if bytes.len() != element_size {
+            return Err(Error::custom(format!(
+                "Extension field element must have exactly {} bytes",
+                element_size
+            )));
+        }
+        let coefficients = bytes
+            .chunks(FIELD_ELEMENT_BYTES)
+            .map(|chunk| {
+                field_element_from_bytes(chunk.try_into().unwrap())
+                    .ok_or_else(|| Error::custom("Field element must be smaller than the modulus"))
+            })
+            .collect::<Result<Vec<MyField>, D::Error>>()?;
+        Ok(MyFieldExt::from_base_prime_field_elems(coefficients).unwrap())
+    }
+}
+
+mod hex_extension_field_elements {
+    use crate::common::finite_fields::MyFieldExt;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct ExtensionFieldElement(#[serde(with = "super::hex_extension_field_element")] MyFieldExt);
+
+    pub fn serialize<S: Serializer>(
+        elements: &[MyFieldExt],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            elements
+                .iter()
+                .map(|element| ExtensionFieldElement(*element)),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<MyFieldExt>, D::Error> {
+        let elements = Vec::<ExtensionFieldElement>::deserialize(deserializer)?;
+        Ok(elements.into_iter().map(|element| element.0).collect())
+    }
+}
@@ -1,3 +1,4 @@
+pub mod air;
 pub mod common;
 pub mod prover;
 pub mod verifier;
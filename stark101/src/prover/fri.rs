@@ -0,0 +1,204 @@
+use crate::common::{
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::{
+        create_fri_merkle_tree, decommit_batched_merkle_proofs, decommit_on_batched_query,
+        fri_folding_factors, fri_leaf_size, generate_batched_fri_commitments, CommittedEvaluations,
+    },
+    hasher::Hasher,
+    merkle::{get_merkle_cap, MerkleTree},
+    options::ProofOptions,
+    polynomials::coset,
+    proof::{FriProof, MerkleCap},
+};
+use log::debug;
+use std::borrow::Cow;
+use std::iter::zip;
+
+// Prover of the FRI low-degree test, independent of any AIR
+// It commits to the evaluations of functions on the coset offset * H, H being the subgroup of size blowup times the
+// degree bound, and proves that they are all the evaluations of polynomials of degree less than the degree bound,
+// running a single FRI on their random linear combination. The options give the parameters of FRI, their hash function
+// must be the one of the channel.
+// The functions are either committed by the prover, or borrowed with the trees the caller committed them with.
+pub struct FriProver<'a, H: Hasher> {
+    options: ProofOptions,
+    domain: Vec<MyField>,
+    folding_factors: Vec<usize>,
+    evaluations: Vec<Cow<'a, [MyFieldExt]>>,
+    merkles: Vec<Cow<'a, MerkleTree<H>>>,
+}
+
+impl<'a, H: Hasher> FriProver<'a, H> {
+    pub fn new(
+        offset: MyField,
+        evaluations: Vec<MyFieldExt>,
+        degree_bound: usize,
+        options: &ProofOptions,
+    ) -> Self {
+        Self::new_batched(offset, vec![evaluations], degree_bound, options)
+    }
+
+    pub fn new_batched(
+        offset: MyField,
+        evaluations: Vec<Vec<MyFieldExt>>,
+        degree_bound: usize,
+        options: &ProofOptions,
+    ) -> Self {
+        let folding_factors = Self::folding_factors(degree_bound, options);
+        let merkles = evaluations
+            .iter()
+            .map(|evaluation| {
+                Cow::Owned(create_fri_merkle_tree(
+                    evaluation,
+                    fri_leaf_size(&folding_factors, 0),
+                ))
+            })
+            .collect();
+        Self::build(
+            offset,
+            evaluations.into_iter().map(Cow::Owned).collect(),
+            merkles,
+            folding_factors,
+            degree_bound,
+            options,
+        )
+    }
+
+    // Batches functions the caller already committed, each tree having the layout of the first FRI layer: its i-th leaf
+    // holds the elements at i + j * len / folding_factor, see fri::CommittedEvaluations
+    pub fn new_committed(
+        offset: MyField,
+        committed: &[CommittedEvaluations<'a, H>],
+        degree_bound: usize,
+        options: &ProofOptions,
+    ) -> Self {
+        let folding_factors = Self::folding_factors(degree_bound, options);
+        let domain_size = options.blowup * degree_bound;
+        assert!(
+            committed.iter().all(|(_, merkle)| {
+                merkle.leaves_len() == domain_size / fri_leaf_size(&folding_factors, 0)
+            }),
+            "Expected trees of {} leaves",
+            domain_size / fri_leaf_size(&folding_factors, 0)
+        );
+        Self::build(
+            offset,
+            committed
+                .iter()
+                .map(|(evaluation, _)| Cow::Borrowed(*evaluation))
+                .collect(),
+            committed
+                .iter()
+                .map(|(_, merkle)| Cow::Borrowed(*merkle))
+                .collect(),
+            folding_factors,
+            degree_bound,
+            options,
+        )
+    }
+
+    fn folding_factors(degree_bound: usize, options: &ProofOptions) -> Vec<usize> {
+        assert!(
+            degree_bound.is_power_of_two(),
+            "Degree bound must be a power of two"
+        );
+        let folding_factors = fri_folding_factors(
+            degree_bound,
+            options.folding_factor,
+            options.max_remainder_degree + 1,
+        );
+        assert!(
+            !folding_factors.is_empty(),
+            "Degree bound must exceed max_remainder_degree + 1"
+        );
+        folding_factors
+    }
+
+    fn build(
+        offset: MyField,
+        evaluations: Vec<Cow<'a, [MyFieldExt]>>,
+        merkles: Vec<Cow<'a, MerkleTree<H>>>,
+        folding_factors: Vec<usize>,
+        degree_bound: usize,
+        options: &ProofOptions,
+    ) -> Self {
+        assert_eq!(
+            options.hash,
+            H::HASH_FUNCTION,
+            "Hash function of the options must be the one of the channel"
+        );
+        assert!(!evaluations.is_empty(), "Expected at least one function");
+        let domain_size = options.blowup * degree_bound;
+        assert!(
+            evaluations
+                .iter()
+                .all(|evaluation| evaluation.len() == domain_size),
+            "Expected {} evaluations of each function",
+            domain_size
+        );
+        Self {
+            options: *options,
+            domain: coset(offset, domain_size),
+            folding_factors,
+            evaluations,
+            merkles,
+        }
+    }
+
+    // Merkle caps committing to the evaluations of each function
+    pub fn commitments(&self) -> Vec<MerkleCap> {
+        self.merkles
+            .iter()
+            .map(|merkle| MerkleCap(get_merkle_cap(merkle, self.options.cap_height)))
+            .collect()
+    }
+
+    // Sends the commitments to the functions, then the ones to the FRI layers of their combination and the remainder,
+    // grinds a nonce and decommits on the queries drawn from the channel
+    pub fn prove(self, channel: &mut Channel<H>) -> FriProof {
+        debug!("Executing FRI prover...");
+        let batched_caps = self.commitments();
+        for cap in &batched_caps {
+            channel.send(&cap.to_bytes());
+        }
+        let committed: Vec<CommittedEvaluations<H>> = zip(&self.evaluations, &self.merkles)
+            .map(|(evaluation, merkle)| (evaluation.as_ref(), merkle.as_ref()))
+            .collect();
+        let (_, (fri_layers, fri_merkles, fri_remainder)) = generate_batched_fri_commitments(
+            &self.domain,
+            &committed,
+            &self.folding_factors,
+            self.options.max_remainder_degree,
+            self.options.cap_height,
+            channel,
+        );
+        let fri_caps = fri_merkles
+            .iter()
+            .map(|merkle| MerkleCap(get_merkle_cap(merkle, self.options.cap_height)))
+            .collect();
+
+        let pow_nonce = channel.grind(self.options.grinding_bits);
+        let ids = channel.receive_random_query_ids(self.options.num_queries, self.domain.len());
+        let queries = ids
+            .iter()
+            .map(|&id| decommit_on_batched_query(id, &committed, &fri_layers, channel))
+            .collect();
+        let (batched_proofs, fri_proofs) = decommit_batched_merkle_proofs(
+            &ids,
+            &committed,
+            &fri_merkles,
+            self.options.cap_height,
+            channel,
+        );
+        FriProof {
+            batched_caps,
+            fri_caps,
+            fri_remainder,
+            pow_nonce,
+            queries,
+            batched_proofs,
+            fri_proofs,
+        }
+    }
+}
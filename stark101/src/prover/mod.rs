@@ -1,34 +1,80 @@
+pub mod fri;
 pub mod part1;
 pub mod part2;
 pub mod part3;
 pub mod part4;
 
-use crate::common::channel::Member;
+use crate::air::Air;
+use crate::common::{
+    finite_fields::MyField,
+    hasher::{Blake3, Hasher, Keccak256, Poseidon, Sha256},
+    merkle::get_merkle_cap,
+    options::{HashFunction, ProofOptions},
+    proof::{MerkleCap, StarkProof},
+};
+use log::{debug, info};
 use std::time::Instant;
 
-pub fn run() -> Vec<Member> {
-    println!("Executing prover...");
+pub fn run<A: Air>(air: &A, trace: &[Vec<MyField>], options: &ProofOptions) -> StarkProof {
+    match options.hash {
+        HashFunction::Sha256 => prove::<A, Sha256>(air, trace, options),
+        HashFunction::Blake3 => prove::<A, Blake3>(air, trace, options),
+        HashFunction::Keccak256 => prove::<A, Keccak256>(air, trace, options),
+        HashFunction::Poseidon => prove::<A, Poseidon>(air, trace, options),
+    }
+}
+
+fn prove<A: Air, H: Hasher>(air: &A, trace: &[Vec<MyField>], options: &ProofOptions) -> StarkProof {
+    debug!("Executing prover...");
+    options.check(air).unwrap_or_else(|err| panic!("{}", err));
     let start = Instant::now();
 
-    let (g, eval_domain, f, f_eval, f_merkle, mut channel) = part1::run();
-    println!("✅ Part1 took: {:?}", start.elapsed());
+    let (eval_domain, trace_eval, trace_merkle, mut channel) =
+        part1::run::<A, H>(air, trace, options);
+    info!("✅ Part1 took: {:?}", start.elapsed());
     let start = Instant::now();
-    assert_eq!(channel.proof.len(), 1, "length of proof must be 1");
 
-    let (CP, CP_eval, CP_merkle) = part2::run(g, &eval_domain, &f, &mut channel);
-    println!("✅ Part2 took: {:?}", start.elapsed());
+    let (CP_eval, CP_merkle) = part2::run(air, &eval_domain, &trace_eval, &mut channel, options);
+    info!("✅ Part2 took: {:?}", start.elapsed());
     let start = Instant::now();
-    assert_eq!(channel.proof.len(), 5, "length of proof must be 5");
 
-    let (fri_layers, fri_merkles) =
-        part3::run(&eval_domain, &CP, &CP_eval, &CP_merkle, &mut channel);
-    println!("✅ Part3 took: {:?}", start.elapsed());
+    let (fri_layers, fri_merkles, fri_remainder) = part3::run(
+        air,
+        &eval_domain,
+        &CP_eval,
+        CP_merkle,
+        &mut channel,
+        options,
+    );
+    info!("✅ Part3 took: {:?}", start.elapsed());
     let start = Instant::now();
-    assert_eq!(channel.proof.len(), 26, "length of proof must be 26");
 
-    part4::run(f_eval, f_merkle, fri_layers, fri_merkles, &mut channel);
-    println!("✅ Part4 took: {:?}", start.elapsed());
-    assert_eq!(channel.proof.len(), 170, "length of proof must be 170");
+    let trace_cap = MerkleCap(get_merkle_cap(&trace_merkle, options.cap_height));
+    let CP_cap = MerkleCap(get_merkle_cap(&fri_merkles[0], options.cap_height));
+    let fri_caps = fri_merkles[1..]
+        .iter()
+        .map(|merkle| MerkleCap(get_merkle_cap(merkle, options.cap_height)))
+        .collect();
+    let (pow_nonce, queries, trace_proof, fri_proofs) = part4::run(
+        air,
+        trace_eval,
+        trace_merkle,
+        fri_layers,
+        fri_merkles,
+        &mut channel,
+        options,
+    );
+    info!("✅ Part4 took: {:?}", start.elapsed());
 
-    channel.proof
+    StarkProof {
+        options: *options,
+        trace_cap,
+        CP_cap,
+        fri_caps,
+        fri_remainder,
+        pow_nonce,
+        queries,
+        trace_proof,
+        fri_proofs,
+    }
 }
@@ -1,94 +1,90 @@
 use super::super::common::{
-    channel::*, finite_fields::MyField, merkle::create_merkle_tree, polynomials::*,
+    channel::*,
+    finite_fields::MyField,
+    hasher::Hasher,
+    merkle::{create_merkle_tree_from_columns, get_merkle_cap, MerkleTree},
+    options::ProofOptions,
+    polynomials::*,
+    proof::{field_elements_to_bytes, MerkleCap},
 };
+use crate::air::Air;
 use ark_ff::{FftField, Field};
-use ark_poly::{univariate::DensePolynomial, Polynomial};
-use rs_merkle::{algorithms::Sha256, MerkleTree};
+use ark_poly::univariate::DensePolynomial;
+use log::debug;
+use rayon::prelude::*;
 
-pub fn run() -> (
-    MyField,
-    Vec<MyField>,
-    DensePolynomial<MyField>,
-    Vec<MyField>,
-    MerkleTree<Sha256>,
-    Channel,
-) {
-    println!("Executing part 1...");
+pub fn run<A: Air, H: Hasher>(
+    air: &A,
+    trace: &[Vec<MyField>],
+    options: &ProofOptions,
+) -> (Vec<MyField>, Vec<Vec<MyField>>, MerkleTree<H>, Channel<H>) {
+    debug!("Executing part 1...");
 
-    // FibonacciSq Trace
-    let mut a = vec![MyField::from(1), MyField::from(3141592)];
-    for i in 2..1023 {
-        a.push(a[i - 2] * a[i - 2] + a[i - 1] * a[i - 1]);
-    }
-    assert!(
-        a.len() == 1023,
-        "The trace must consist of exactly 1023 elements."
-    );
+    // Trace
     assert!(
-        a[1022] == MyField::from(2338775057u64),
-        "Wrong last element!"
+        air.is_valid_trace(trace),
+        "The trace does not satisfy the constraints of the AIR."
     );
 
     // Thinking of polynomials
-    // Create a Group of size 1024
-    let g = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 1024]));
-    let mut G = vec![MyField::ONE];
-    for i in 1..1024 {
-        G.push(G[i - 1] * g);
-    }
+    // The trace domain is the Group G of size n generated by g
+    let n = air.trace_domain_size();
+    let g = air.trace_generator();
     assert!(
-        g.pow(&(vec![1024])) == MyField::ONE,
+        g.pow([n as u64]) == MyField::ONE && g.pow([n as u64 / 2]) != MyField::ONE,
         "The generator g is of wrong order"
     );
-    assert!(G[1023] * g == MyField::ONE, "Wrong last element!");
-    // Interpolate the polynomial
-    let f = interpolate_polynomial(&G[..1023].to_vec(), &a);
-    assert!(
-        f.evaluate(&MyField::from(2)) == MyField::from(1302089273),
-        "Evaluation at point 2 is wrong!"
-    );
+    // Interpolate one polynomial per column of the trace, the cells of a column being its values on 1, g, g^2, ..
+    let trace_polys: Vec<DensePolynomial<MyField>> = trace
+        .par_iter()
+        .map(|column| interpolate_on_subgroup(column))
+        .collect();
 
     // Evaluating on a Larger Domain
-    // The trace, viewed as evaluations of a polynomial f on G , can now be extended by evaluating over a
-    // larger domain, thereby creating a Reed-Solomon error correction code.
-    // Create a Group of size 8192
-    let h = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 8192]));
+    // Each column of the trace, viewed as evaluations of a polynomial on G , can now be extended by evaluating over
+    // a larger domain, thereby creating a Reed-Solomon error correction code.
+    // Create a Group blowup times larger than both the trace domain and the degree bound of the composition polynomial
+    let eval_domain_size = options.eval_domain_size(air);
+    let h = MyField::GENERATOR.pow([3221225472_u64 / eval_domain_size as u64]);
     let mut H = vec![MyField::ONE];
-    for i in 1..8192 {
+    for i in 1..eval_domain_size {
         H.push(H[i - 1] * h);
     }
     assert!(
-        h.pow(&(vec![8192])) == MyField::ONE,
+        h.pow([eval_domain_size as u64]) == MyField::ONE,
         "The generator h is of wrong order"
     );
-    assert!(H[8191] * h == MyField::ONE, "Wrong last element!");
+    assert!(
+        H[eval_domain_size - 1] * h == MyField::ONE,
+        "Wrong last element!"
+    );
     // Construct eval domain
     let w = MyField::GENERATOR;
     let w_inv = w.inverse().unwrap();
     let eval_domain: Vec<MyField> = H.iter().map(|x| w * x).collect();
-    for i in 0..8192 {
+    for i in 0..eval_domain_size {
         assert!(
-            ((w_inv * eval_domain[1]).pow(&(vec![i]))) * w == eval_domain[i as usize],
+            ((w_inv * eval_domain[1]).pow([i as u64])) * w == eval_domain[i],
             "element of eval_domain is wrong!"
         );
     }
     // Evaluate on the Coset
-    let f_eval: Vec<MyField> = eval_domain.iter().map(|point| f.evaluate(&point)).collect();
-    assert!(
-        f_eval[0] == MyField::from(576067152),
-        "Wrong first element of f_eval!"
-    );
-    assert!(
-        f_eval[8191] == MyField::from(1076821037),
-        "Wrong last element of f_eval!"
-    );
+    let trace_eval: Vec<Vec<MyField>> = trace_polys
+        .par_iter()
+        .map(|poly| evaluate_on_coset(poly, w, eval_domain_size))
+        .collect();
 
     // Commitments
-    // We will use Sha256-based Merkle Trees as our commitment scheme
-    let f_merkle = create_merkle_tree(&f_eval);
+    // We will use Merkle Trees over the hash function of the options as our commitment scheme
+    // A single tree commits to all the columns, each leaf hashing the row of evaluations at a point of the coset, and
+    // its Merkle cap is sent in place of its root
+    let trace_merkle = create_merkle_tree_from_columns(&trace_eval);
     // Channel
-    let mut channel = Channel::new();
-    channel.send(&f_merkle.root().unwrap().to_vec());
+    // The options and the public inputs are absorbed first so that the challenges depend on the statement
+    let mut channel = Channel::<H>::default();
+    channel.send(&options.to_bytes());
+    channel.send(&field_elements_to_bytes(&air.public_inputs()));
+    channel.send(&MerkleCap(get_merkle_cap(&trace_merkle, options.cap_height)).to_bytes());
 
-    (g, eval_domain, f, f_eval, f_merkle, channel)
+    (eval_domain, trace_eval, trace_merkle, channel)
 }
@@ -1,116 +1,60 @@
+use crate::air::Air;
 use crate::common::{
     channel::Channel,
-    finite_fields::MyField,
-    merkle::create_merkle_tree,
-    polynomials::{compose_polynomials, pow},
+    finite_fields::{MyField, MyFieldExt},
+    fri::{create_fri_merkle_tree, fri_leaf_size},
+    hasher::Hasher,
+    merkle::{get_merkle_cap, MerkleTree},
+    options::ProofOptions,
+    proof::MerkleCap,
 };
-use ark_ff::{AdditiveGroup, Field};
-use ark_poly::{
-    univariate::{DenseOrSparsePolynomial, DensePolynomial},
-    Polynomial,
-};
-use rs_merkle::{algorithms::Sha256, MerkleTree};
+use ark_ff::AdditiveGroup;
+use log::debug;
+use rayon::prelude::*;
+use std::iter::zip;
 
-pub fn run(
-    g: MyField,
-    eval_domain: &Vec<MyField>,
-    f: &DensePolynomial<MyField>,
-    channel: &mut Channel,
-) -> (DensePolynomial<MyField>, Vec<MyField>, MerkleTree<Sha256>) {
-    println!("Executing part 2...");
+pub fn run<A: Air, H: Hasher>(
+    air: &A,
+    eval_domain: &[MyField],
+    trace_eval: &[Vec<MyField>],
+    channel: &mut Channel<H>,
+    options: &ProofOptions,
+) -> (Vec<MyFieldExt>, MerkleTree<H>) {
+    debug!("Executing part 2...");
 
-    // Rational Functions (That are in Fact Polynomials)
-    // The first constraint
-    let numer0 = f - &DensePolynomial {
-        coeffs: vec![MyField::ONE],
-    };
-    let denom0 = DensePolynomial {
-        coeffs: vec![-MyField::ONE, MyField::ONE],
-    }; // x-1
-    let (p0, r0) =
-        DenseOrSparsePolynomial::divide_with_q_and_r(&(&numer0).into(), &(&denom0).into()).unwrap();
-    assert_eq!(
-        r0,
-        DensePolynomial { coeffs: vec![] },
-        "numer0 should be divisble by x-1"
-    );
-    assert_eq!(
-        p0.evaluate(&MyField::from(2718)),
-        MyField::from(2509888982_u32)
-    );
-    // The second constraint
-    let numer1 = f - &DensePolynomial {
-        coeffs: vec![MyField::from(2338775057_u32)],
-    };
-    let denom1 = DensePolynomial {
-        coeffs: vec![-g.pow(&(vec![1022])), MyField::ONE],
-    }; // x - g**1022
-    let (p1, r1) =
-        DenseOrSparsePolynomial::divide_with_q_and_r(&(&numer1).into(), &(&denom1).into()).unwrap();
-    assert_eq!(
-        r1,
-        DensePolynomial { coeffs: vec![] },
-        "numer1 should be divisible by x-g**1022"
-    );
-    assert_eq!(
-        p1.evaluate(&MyField::from(5772)),
-        MyField::from(232961446_u32)
-    );
-    // The third constraint
-    let t20 = DensePolynomial {
-        coeffs: vec![MyField::ZERO, g.pow(&(vec![2]))],
-    }; // g**2 x
-    let t21 = DensePolynomial {
-        coeffs: vec![MyField::ZERO, g],
-    }; // g x
-    let numer2 =
-        &compose_polynomials(&f, &t20) - &pow(&compose_polynomials(&f, &t21), 2) - &pow(&f, 2);
-    assert_eq!(numer2.evaluate(&g.pow(&(vec![1020]))), MyField::ZERO);
-    assert_ne!(numer2.evaluate(&g.pow(&(vec![1021]))), MyField::ZERO);
-    let mut t22_coeffs = vec![MyField::from(-1)];
-    t22_coeffs.extend(vec![MyField::ZERO; 1023]);
-    t22_coeffs.push(MyField::ONE);
-    let t22 = DensePolynomial { coeffs: t22_coeffs }; // x^1024 - 1
-    let t23 = DensePolynomial {
-        coeffs: vec![-g.pow(&(vec![1021])), MyField::ONE],
-    }; // x - g**1021
-    let t24 = DensePolynomial {
-        coeffs: vec![-g.pow(&(vec![1022])), MyField::ONE],
-    }; // x - g**1022
-    let t25 = DensePolynomial {
-        coeffs: vec![-g.pow(&(vec![1023])), MyField::ONE],
-    }; // x - g**1023
-    let (denom2, _) = DenseOrSparsePolynomial::divide_with_q_and_r(
-        &(&t22).into(),
-        &(&(&t23 * &t24 * &t25)).into(),
-    )
-    .unwrap();
-    let (p2, r2) =
-        DenseOrSparsePolynomial::divide_with_q_and_r(&(&numer2).into(), &(&denom2).into()).unwrap();
-    assert_eq!(
-        r2,
-        DensePolynomial { coeffs: vec![] },
-        "numer2 should be divisible by (x**1024 - 1) / ((x - g**1021) * (x - g**1022) * (x - g**1023))"
-    );
-    assert_eq!(
-        p2.evaluate(&MyField::from(31415)),
-        MyField::from(2090051528_u32)
-    );
     // Composition polynomial
-    let alpha0 = channel.receive_random_field_element();
-    let alpha1 = channel.receive_random_field_element();
-    let alpha2 = channel.receive_random_field_element();
-    let CP = &p0 * alpha0 + &p1 * alpha1 + &p2 * alpha2;
-    assert_eq!(CP.degree(), 1023, "The degree of CP must be 1023");
+    // Each constraint gets its own random coefficient, drawn from the extension field so that CP is in the extension
+    let alphas = channel.receive_random_extension_field_elements(air.num_constraints());
     // Evaluate on the Coset
-    let CP_eval: Vec<MyField> = eval_domain
-        .iter()
-        .map(|point| CP.evaluate(&point))
+    // The constraints are evaluated pointwise: for a point x of the eval domain, the rows of the trace polynomials at
+    // x, gx, g^2x, .. are found eval_domain.len() / trace_domain_size positions apart in trace_eval.
+    // The divisors of the constraints are inverted once for the whole eval domain, and each worker fills the same frame
+    // for all its points.
+    let eval_domain_size = eval_domain.len();
+    let row_offset = eval_domain_size / air.trace_domain_size();
+    let divisors = air.composition_divisors_on_coset(eval_domain);
+    let CP_eval: Vec<MyFieldExt> = (0..eval_domain_size)
+        .into_par_iter()
+        .map_init(
+            || vec![vec![MyField::ZERO; air.trace_width()]; air.transition_frame_size()],
+            |frame, i| {
+                for (k, row) in frame.iter_mut().enumerate() {
+                    for (cell, column) in zip(row, trace_eval) {
+                        *cell = column[(i + k * row_offset) % eval_domain_size];
+                    }
+                }
+                air.evaluate_composition_with(i, frame, &divisors, &alphas)
+            },
+        )
         .collect();
     // Commitment
-    let CP_merkle = create_merkle_tree(&CP_eval);
+    // CP is the first FRI layer, so that its leaves hold the cosets of elements folded together
+    let CP_merkle = create_fri_merkle_tree(
+        &CP_eval,
+        fri_leaf_size(&options.fri_folding_factors(air), 0),
+    );
     // send on Channel
-    channel.send(&CP_merkle.root().unwrap().to_vec());
+    channel.send(&MerkleCap(get_merkle_cap(&CP_merkle, options.cap_height)).to_bytes());
 
-    (CP, CP_eval, CP_merkle)
+    (CP_eval, CP_merkle)
 }
@@ -1,45 +1,69 @@
-use crate::common::{channel::Channel, finite_fields::MyField, fri::generate_fri_commitments};
-use ark_ff::Field;
-use ark_poly::{univariate::DensePolynomial, Polynomial};
-use rs_merkle::{algorithms::Sha256, MerkleTree};
+use crate::air::Air;
+use crate::common::{
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::generate_fri_commitments,
+    hasher::Hasher,
+    merkle::MerkleTree,
+    options::ProofOptions,
+};
+use ark_ff::{AdditiveGroup, Field};
+use log::debug;
 
-pub fn run(
-    eval_domain: &Vec<MyField>,
-    CP: &DensePolynomial<MyField>,
-    CP_eval: &Vec<MyField>,
-    CP_merkle: &MerkleTree<Sha256>,
-    channel: &mut Channel,
-) -> (Vec<Vec<MyField>>, Vec<MerkleTree<Sha256>>) {
-    println!("Executing part 3...");
+pub fn run<A: Air, H: Hasher>(
+    air: &A,
+    eval_domain: &[MyField],
+    CP_eval: &[MyFieldExt],
+    CP_merkle: MerkleTree<H>,
+    channel: &mut Channel<H>,
+    options: &ProofOptions,
+) -> (Vec<Vec<MyFieldExt>>, Vec<MerkleTree<H>>, Vec<MyFieldExt>) {
+    debug!("Executing part 3...");
 
     // FRI folding
     // Compute subsequent FRI domain
-    let half_domain_size = eval_domain.len() / 2;
+    // The elements of a coset of the eval domain folded together have the same power folding_factor
+    let folding_factors = options.fri_folding_factors(air);
+    if let Some(&folding_factor) = folding_factors.first() {
+        let coset_offset = eval_domain.len() / folding_factor;
+        assert_eq!(
+            eval_domain[0].pow([folding_factor as u64]),
+            eval_domain[coset_offset].pow([folding_factor as u64])
+        );
+    }
+    // Generate FRI commitments
+    // Each layer divides the degree bound of CP by its folding factor, until reaching the degree bound of the remainder
+    let (fri_layers, fri_merkles, fri_remainder) = generate_fri_commitments(
+        eval_domain,
+        CP_eval,
+        CP_merkle,
+        &folding_factors,
+        options.max_remainder_degree,
+        options.cap_height,
+        channel,
+    );
+    let remainder_degree_bound = air
+        .composition_degree_bound()
+        .min(options.max_remainder_degree + 1);
     assert_eq!(
-        eval_domain[100].pow(&(vec![2])),
-        eval_domain[half_domain_size + 100].pow(&(vec![2]))
+        fri_layers.len(),
+        folding_factors.len() + 1,
+        "Expected number of FRI layers is {}",
+        folding_factors.len() + 1
     );
-    // Generate FRI commitments
-    let (fri_polys, _, fri_layers, fri_merkles) =
-        generate_fri_commitments(&CP, &eval_domain, &CP_eval, &CP_merkle, channel);
-    assert_eq!(fri_layers.len(), 11, "Expected number of FRI layers is 11");
     assert_eq!(
-        fri_layers.last().unwrap().len(),
-        8,
-        "Expected last layer to contain exactly 8 elements"
+        fri_layers.last().unwrap().len() * air.composition_degree_bound(),
+        eval_domain.len() * remainder_degree_bound,
+        "Expected last layer to contain exactly {} elements",
+        eval_domain.len() * remainder_degree_bound / air.composition_degree_bound()
     );
+    // The layers are folded in evaluation form, so the degree of CP is only checked through the one of the remainder
     assert!(
-        fri_layers
-            .last()
-            .unwrap()
+        fri_remainder[remainder_degree_bound..]
             .iter()
-            .all(|&x| x == MyField::from(1150958405)),
-        "Expected last layer to be constant"
-    );
-    assert_eq!(
-        fri_polys.last().unwrap().degree(),
-        0,
-        "Expacted last polynomial to be constant (degree 0)"
+            .all(|coeff| *coeff == MyFieldExt::ZERO),
+        "Expected remainder to have degree less than {}",
+        remainder_degree_bound
     );
-    (fri_layers, fri_merkles)
+    (fri_layers, fri_merkles, fri_remainder)
 }
@@ -1,27 +1,64 @@
-use crate::common::{channel::Channel, finite_fields::MyField, fri::decommit_on_query};
-use rs_merkle::{algorithms::Sha256, MerkleTree};
+use crate::air::Air;
+use crate::common::{
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::{decommit_merkle_proofs, decommit_on_query},
+    hasher::Hasher,
+    merkle::MerkleTree,
+    options::ProofOptions,
+    proof::{MerkleProof, QueryOpening},
+};
+use log::debug;
 
-pub fn run(
-    f_eval: Vec<MyField>,
-    f_merkle: MerkleTree<Sha256>,
-    fri_layers: Vec<Vec<MyField>>,
-    fri_merkles: Vec<MerkleTree<Sha256>>,
-    channel: &mut Channel,
-) {
-    println!("Executing part 4...");
+pub fn run<A: Air, H: Hasher>(
+    air: &A,
+    trace_eval: Vec<Vec<MyField>>,
+    trace_merkle: MerkleTree<H>,
+    fri_layers: Vec<Vec<MyFieldExt>>,
+    fri_merkles: Vec<MerkleTree<H>>,
+    channel: &mut Channel<H>,
+    options: &ProofOptions,
+) -> (u64, Vec<QueryOpening>, MerkleProof, Vec<MerkleProof>) {
+    debug!("Executing part 4...");
 
-    // Decommit on a Set of 3 Queries
-    // Prover gets a set of random queries from the channel, i.e., indices between 0 to 8191, and decommits on each query.
+    // Proof-of-Work
+    // Prover grinds a nonce on the state reached after the FRI commitments, so that each attempt at drawing other
+    // queries costs 2^grinding_bits hashes.
+    let pow_nonce = channel.grind(options.grinding_bits);
+
+    // Decommit on a Set of Queries
+    // Prover gets a set of distinct random queries from the channel, i.e., indices of the whole eval domain, the frame
+    // of rows at x, gx, g^2x, .. wrapping around its end, and decommits on each query.
+    // The openings of all the queries are then authenticated by one multi-proof per Merkle tree, sharing the nodes
+    // common to several openings.
     // Difficult to parallelise as we write data in channel sequentially
-    for _ in 0..3 {
-        let id = channel.receive_random_int(0, 8191 - 16);
-        decommit_on_query(
-            id.try_into().unwrap(),
-            &f_eval,
-            &f_merkle,
-            &fri_layers,
-            &fri_merkles,
-            channel,
-        );
-    }
+    let eval_domain_size = trace_eval[0].len();
+    let row_offset = eval_domain_size / air.trace_domain_size();
+    let frame_size = air.transition_frame_size();
+    let ids = channel.receive_random_query_ids(options.num_queries, eval_domain_size);
+    let queries = ids
+        .iter()
+        .map(|&id| {
+            decommit_on_query(
+                id,
+                row_offset,
+                frame_size,
+                &trace_eval,
+                &fri_layers,
+                channel,
+            )
+        })
+        .collect();
+    // The trees of the folded layers are opened, not the one of CP when it is sent as the remainder
+    let num_folded_layers = fri_layers.len() - 1;
+    let (trace_proof, fri_proofs) = decommit_merkle_proofs(
+        &ids,
+        row_offset,
+        frame_size,
+        &trace_merkle,
+        &fri_merkles[..num_folded_layers],
+        options.cap_height,
+        channel,
+    );
+    (pow_nonce, queries, trace_proof, fri_proofs)
 }
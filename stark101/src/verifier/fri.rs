@@ -0,0 +1,209 @@
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use rayon::prelude::*;
+
+use crate::common::{
+    channel::Channel,
+    finite_fields::MyField,
+    fri::{
+        batch_fri_openings, check_batched_merkle_proofs, check_decommittment_on_fri_layers,
+        check_fri_caps_shape, check_fri_openings_shape, check_fri_remainder_degree,
+        fri_folding_factors,
+    },
+    hasher::Hasher,
+    merkle::merkle_cap_len,
+    options::ProofOptions,
+    proof::{extension_field_elements_to_bytes, FriProof},
+};
+use crate::verifier::VerifierError;
+use log::debug;
+
+// Verifier of the FRI low-degree test of a batch of functions, independent of any AIR, see prover::fri
+pub struct FriVerifier {
+    options: ProofOptions,
+    offset: MyField,
+    domain_size: usize,
+    folding_factors: Vec<usize>,
+    remainder_degree_bound: usize,
+}
+
+impl FriVerifier {
+    pub fn new(offset: MyField, degree_bound: usize, options: &ProofOptions) -> Self {
+        assert!(
+            degree_bound.is_power_of_two(),
+            "Degree bound must be a power of two"
+        );
+        let folding_factors = fri_folding_factors(
+            degree_bound,
+            options.folding_factor,
+            options.max_remainder_degree + 1,
+        );
+        assert!(
+            !folding_factors.is_empty(),
+            "Degree bound must exceed max_remainder_degree + 1"
+        );
+        Self {
+            options: *options,
+            offset,
+            domain_size: options.blowup * degree_bound,
+            folding_factors,
+            remainder_degree_bound: degree_bound.min(options.max_remainder_degree + 1),
+        }
+    }
+
+    // Checks a FRI proof of num_functions functions, the transcript being rebuilt on the channel in the order of the
+    // prover
+    // The queries open the tested evaluations in their batched cosets, see fri::fri_opened_value. The hash function of
+    // the options must be the one of the channel.
+    pub fn verify<H: Hasher>(
+        &self,
+        num_functions: usize,
+        proof: &FriProof,
+        channel: &mut Channel<H>,
+    ) -> Result<(), VerifierError> {
+        debug!("Executing FRI verifier...");
+        if self.options.hash != H::HASH_FUNCTION {
+            return Err(VerifierError::InvalidOptions(
+                "Hash function of the options must be the one of the channel".to_string(),
+            ));
+        }
+
+        // check shape
+        // The batched functions are shaped as the first FRI layer, the other layers but the last one are committed
+        let num_queries = self.options.num_queries;
+        let num_fri_layers = self.folding_factors.len();
+        let domain_size = self.domain_size;
+        if proof.batched_caps.len() != num_functions {
+            return Err(VerifierError::MalformedProof(format!(
+                "Proof must contain {} batched caps",
+                num_functions
+            )));
+        }
+        let batched_cap_len = merkle_cap_len(
+            domain_size / self.folding_factors[0],
+            self.options.cap_height,
+        );
+        if proof
+            .batched_caps
+            .iter()
+            .any(|cap| cap.0.len() != batched_cap_len)
+        {
+            return Err(VerifierError::MalformedProof(format!(
+                "Batched caps must contain {} nodes",
+                batched_cap_len
+            )));
+        }
+        if proof.fri_caps.len() != num_fri_layers - 1 {
+            return Err(VerifierError::MalformedProof(format!(
+                "Proof must contain {} FRI caps",
+                num_fri_layers - 1
+            )));
+        }
+        check_fri_caps_shape(
+            domain_size,
+            &self.folding_factors,
+            1,
+            self.options.cap_height,
+            &proof.fri_caps,
+        )?;
+        if proof.fri_remainder.len() != self.options.max_remainder_degree + 1 {
+            return Err(VerifierError::MalformedProof(format!(
+                "Remainder must have {} coefficients",
+                self.options.max_remainder_degree + 1
+            )));
+        }
+        check_fri_remainder_degree(&proof.fri_remainder, self.remainder_degree_bound)?;
+        if proof.batched_proofs.len() != num_functions {
+            return Err(VerifierError::MalformedProof(format!(
+                "Proof must contain {} batched proofs",
+                num_functions
+            )));
+        }
+        if proof.fri_proofs.len() != num_fri_layers - 1 {
+            return Err(VerifierError::MalformedProof(format!(
+                "Proof must contain {} FRI layer proofs",
+                num_fri_layers - 1
+            )));
+        }
+        if num_queries > domain_size {
+            return Err(VerifierError::MalformedProof(format!(
+                "Number of queries must not exceed {}",
+                domain_size
+            )));
+        }
+        if proof.queries.len() != num_queries {
+            return Err(VerifierError::MalformedProof(format!(
+                "Proof must contain {} queries",
+                num_queries
+            )));
+        }
+        for query in &proof.queries {
+            if query.batched.len() != num_functions
+                || query
+                    .batched
+                    .iter()
+                    .any(|opening| opening.values.len() != self.folding_factors[0])
+            {
+                return Err(VerifierError::MalformedProof(format!(
+                    "Queries must open {} batched cosets of {} elements",
+                    num_functions, self.folding_factors[0]
+                )));
+            }
+            check_fri_openings_shape(&self.folding_factors, 1, &query.fri_layers)?;
+        }
+
+        // Rebuild the transcript on the channel
+        for cap in &proof.batched_caps {
+            channel.send(&cap.to_bytes());
+        }
+        let coefficients = channel.receive_random_extension_field_elements(num_functions);
+        let mut betas = vec![];
+        for layer in 0..num_fri_layers {
+            betas.push(channel.receive_random_extension_field_element());
+            if let Some(fri_cap) = proof.fri_caps.get(layer) {
+                channel.send(&fri_cap.to_bytes());
+            }
+        }
+        channel.send(&extension_field_elements_to_bytes(&proof.fri_remainder));
+        channel.replay_proof_of_work(proof.pow_nonce, self.options.grinding_bits)?;
+        let ids = channel.receive_random_query_ids(num_queries, domain_size);
+        if proof.queries.iter().map(|query| query.id).ne(ids) {
+            return Err(VerifierError::ChallengeMismatch);
+        }
+        for query in &proof.queries {
+            for opening in query.batched.iter().chain(&query.fri_layers) {
+                channel.send(&extension_field_elements_to_bytes(&opening.values));
+            }
+        }
+        for merkle_proof in proof.batched_proofs.iter().chain(&proof.fri_proofs) {
+            channel.send(&merkle_proof.to_bytes());
+        }
+
+        // The openings are checked against the caps, then the combination of the batched cosets must fold into the
+        // next layer, and so on
+        check_batched_merkle_proofs::<H>(
+            domain_size,
+            &self.folding_factors,
+            &proof.batched_caps,
+            &proof.fri_caps,
+            &proof.queries,
+            &proof.batched_proofs,
+            &proof.fri_proofs,
+        )?;
+        let fri_remainder = DensePolynomial::from_coefficients_slice(&proof.fri_remainder);
+        (0..num_queries).into_par_iter().try_for_each(|query| {
+            let opening = &proof.queries[query];
+            let mut fri_layers = vec![batch_fri_openings(&opening.batched, &coefficients)];
+            fri_layers.extend(opening.fri_layers.iter().cloned());
+            check_decommittment_on_fri_layers(
+                self.offset,
+                domain_size,
+                &betas,
+                &self.folding_factors,
+                query,
+                opening.id,
+                &fri_layers,
+                &fri_remainder,
+            )
+        })
+    }
+}
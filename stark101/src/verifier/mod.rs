@@ -1,105 +1,226 @@
+pub mod fri;
+
+pub use crate::common::error::VerifierError;
+
 use ark_ff::{FftField, Field};
-use rayon::iter::IntoParallelIterator;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
 use rayon::prelude::*;
 
+use crate::air::Air;
 use crate::common::{
-    channel::{
-        parse_received_field_element, parse_received_int, parse_sent_authentication_path,
-        parse_sent_field_element, parse_sent_root, Member,
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::{
+        check_decommittment_on_fri_layers, check_fri_caps_shape, check_fri_openings_shape,
+        check_fri_remainder_degree, check_merkle_proofs, fri_opened_value,
     },
-    finite_fields::MyField,
-    fri::check_decommittment_on_query,
+    hasher::{Blake3, Hasher, Keccak256, Poseidon, Sha256},
+    merkle::merkle_cap_len,
+    options::{HashFunction, ProofOptions},
+    polynomials::coset_element,
+    proof::{extension_field_elements_to_bytes, field_elements_to_bytes, StarkProof},
 };
+use log::{debug, info};
 use std::time::Instant;
 
-pub fn run(proof: Vec<Member>) -> bool {
-    println!("Executing verifier...");
-    let start = Instant::now();
+pub fn verify<A: Air>(
+    air: &A,
+    proof: &StarkProof,
+    options: &ProofOptions,
+) -> Result<(), VerifierError> {
+    options.check(air).map_err(VerifierError::InvalidOptions)?;
+    match options.hash {
+        HashFunction::Sha256 => verify_with::<A, Sha256>(air, proof, options),
+        HashFunction::Blake3 => verify_with::<A, Blake3>(air, proof, options),
+        HashFunction::Keccak256 => verify_with::<A, Keccak256>(air, proof, options),
+        HashFunction::Poseidon => verify_with::<A, Poseidon>(air, proof, options),
+    }
+}
 
-    // check length
-    assert_eq!(proof.len(), 170, "Length of proof must be 170");
+fn verify_with<A: Air, H: Hasher>(
+    air: &A,
+    proof: &StarkProof,
+    options: &ProofOptions,
+) -> Result<(), VerifierError> {
+    debug!("Executing verifier...");
+    let start = Instant::now();
 
-    // Create a Group of size 8192
-    let h = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 8192]));
-    let mut H = vec![MyField::ONE];
-    for i in 1..8192 {
-        H.push(H[i - 1] * h);
+    // check shape
+    // The proof must contain one Merkle cap and one multi-proof per FRI layer and, for each query, a frame of trace rows
+    // and one opening of the size of its folding factor per FRI layer. Each cap holds 2^cap_height nodes, or all the
+    // leaves of a smaller tree.
+    let num_queries = options.num_queries;
+    let folding_factors = options.fri_folding_factors(air);
+    let num_fri_layers = folding_factors.len();
+    let frame_size = air.transition_frame_size();
+    if proof.options != *options {
+        return Err(VerifierError::OptionsMismatch);
     }
-    // Construct eval domain
-    let w = MyField::GENERATOR;
-    let eval_domain: Vec<MyField> = H.iter().map(|x| w * x).collect();
-
-    let f_merkle_root = parse_sent_root(&proof[0]); // member 0
-    let mut alphas: Vec<MyField> = vec![];
-    for i in 0..3 {
-        alphas.push(parse_received_field_element(&proof[1 + i])); // members [1,3]
+    // The last FRI layer is not committed
+    if proof.fri_caps.len() != num_fri_layers.saturating_sub(1) {
+        return Err(VerifierError::MalformedProof(format!(
+            "Proof must contain {} FRI caps",
+            num_fri_layers.saturating_sub(1)
+        )));
     }
-    let CP_merkle_root = parse_sent_root(&proof[4]); // member 4
-
-    let mut betas = vec![];
-    let mut fri_polys_merkle_roots = vec![CP_merkle_root];
-    for i in 0..10 {
-        betas.push(parse_received_field_element(&proof[5 + 2 * i])); // members 5, 7, .. 23
-        fri_polys_merkle_roots.push(parse_sent_root(&proof[5 + 2 * i + 1])); // members 6, 8, .. 24
+    if proof.fri_remainder.len() != options.max_remainder_degree + 1 {
+        return Err(VerifierError::MalformedProof(format!(
+            "Remainder must have {} coefficients",
+            options.max_remainder_degree + 1
+        )));
+    }
+    // The last FRI layer has the degree bound of CP when no layer is folded
+    check_fri_remainder_degree(
+        &proof.fri_remainder,
+        air.composition_degree_bound()
+            .min(options.max_remainder_degree + 1),
+    )?;
+    let eval_domain_size = options.eval_domain_size(air);
+    let trace_cap_len = merkle_cap_len(eval_domain_size, options.cap_height);
+    if proof.trace_cap.0.len() != trace_cap_len {
+        return Err(VerifierError::MalformedProof(format!(
+            "Trace cap must contain {} nodes",
+            trace_cap_len
+        )));
+    }
+    let mut fri_polys_merkle_caps = vec![proof.CP_cap.clone()];
+    fri_polys_merkle_caps.extend(proof.fri_caps.iter().cloned());
+    check_fri_caps_shape(
+        eval_domain_size,
+        &folding_factors,
+        0,
+        options.cap_height,
+        &fri_polys_merkle_caps,
+    )?;
+    if proof.fri_proofs.len() != num_fri_layers {
+        return Err(VerifierError::MalformedProof(format!(
+            "Proof must contain {} FRI layer proofs",
+            num_fri_layers
+        )));
+    }
+    if proof.queries.len() != num_queries {
+        return Err(VerifierError::MalformedProof(format!(
+            "Proof must contain {} queries",
+            num_queries
+        )));
+    }
+    for query in &proof.queries {
+        if query.trace_frame.len() != frame_size {
+            return Err(VerifierError::MalformedProof(format!(
+                "Queries must open {} trace rows",
+                frame_size
+            )));
+        }
+        if query
+            .trace_frame
+            .iter()
+            .any(|opening| opening.row.len() != air.trace_width())
+        {
+            return Err(VerifierError::MalformedProof(format!(
+                "Trace rows must contain {} elements",
+                air.trace_width()
+            )));
+        }
+        check_fri_openings_shape(&folding_factors, 0, &query.fri_layers)?;
     }
-    let fri_constant_poly_commit = parse_sent_field_element(&proof[25]); // member 25
-
-    // Prover Decommitted on a Set of 3 Queries
-    // We verify the data's consistency for each Query
-    // We process the verifications in parallel
-    (0..3).into_par_iter().for_each(|query| {
-        let id = parse_received_int(&proof[26 + 48 * query]) as usize; //members 26, 74, 122
 
-        let f_id = parse_sent_field_element(&proof[27 + 48 * query]); // members 27, 75, 123
-        let authentication_path_f_id = parse_sent_authentication_path(&proof[28 + 48 * query]); // members 28, 76, 124
-        let f_g_id = parse_sent_field_element(&proof[29 + 48 * query]); // members 29, 77, 125
-        let authentication_path_f_g_id = parse_sent_authentication_path(&proof[30 + 48 * query]); // members 30, 78, 126
-        let f_g2_id = parse_sent_field_element(&proof[31 + 48 * query]); // members 31, 79, 127
-        let authentication_path_f_g2_id = parse_sent_authentication_path(&proof[32 + 48 * query]); // members 32, 80, 128
+    // The eval domain is the coset of w of a Group blowup times larger than both the trace domain and the degree bound
+    // of the composition polynomial
+    // Only its points at the queries are computed, see polynomials::coset_element.
+    let trace_domain_size = air.trace_domain_size();
+    let row_offset = eval_domain_size / trace_domain_size;
+    let w = MyField::GENERATOR;
 
-        let mut fri_poly_id = vec![];
-        let mut authentication_path_fri_poly_id = vec![];
-        let mut fri_poly_sibling = vec![];
-        let mut authentication_path_fri_poly_sibling = vec![];
-        for i in 0..10 {
-            fri_poly_id.push(parse_sent_field_element(&proof[33 + 4 * i + 48 * query])); // members (33, 37, .. 69), (81, 85, .. 117), (129, 133, .. 165)
-            authentication_path_fri_poly_id.push(parse_sent_authentication_path(
-                &proof[33 + 4 * i + 1 + 48 * query],
-            )); // members (34, 38, .., 70), (82, 86, .. 118), (130, 131, .. 166)
-            fri_poly_sibling.push(parse_sent_field_element(
-                &proof[33 + 4 * i + 2 + 48 * query],
-            )); // members (35, 39, .. 71), (83, 87, .. 119), (131, 132, .. 167)
-            authentication_path_fri_poly_sibling.push(parse_sent_authentication_path(
-                &proof[33 + 4 * i + 3 + 48 * query],
-            )); // members (36, 40, .., 72), (84, 88, .. 120), (132, 133, .. 168)
+    // Rebuild the transcript on our own channel
+    // The commitments are sent in the order of the prover and the random challenges are derived from the channel
+    // state, so that the prover cannot choose the random challenges used by the checks below.
+    let mut channel = Channel::<H>::default();
+    channel.send(&options.to_bytes());
+    channel.send(&field_elements_to_bytes(&air.public_inputs()));
+    channel.send(&proof.trace_cap.to_bytes());
+    let alphas = channel.receive_random_extension_field_elements(air.num_constraints());
+    channel.send(&proof.CP_cap.to_bytes());
+    let mut betas = vec![];
+    for layer in 0..num_fri_layers {
+        betas.push(channel.receive_random_extension_field_element());
+        if let Some(fri_cap) = proof.fri_caps.get(layer) {
+            channel.send(&fri_cap.to_bytes());
         }
-        let fri_constant_poly_decommit = parse_sent_field_element(&proof[73 + 48 * query]); // members 73, 121, 169
-        assert_eq!(
-            fri_constant_poly_commit, fri_constant_poly_decommit,
-            "Constant polynomial should be the same between commit and decommit"
-        );
-        // Add the constant poly to check the consistency of last FRI layer
-        fri_poly_id.push(fri_constant_poly_decommit);
+    }
+    channel.send(&extension_field_elements_to_bytes(&proof.fri_remainder));
+    channel.replay_proof_of_work(proof.pow_nonce, options.grinding_bits)?;
+    let ids = channel.receive_random_query_ids(num_queries, eval_domain_size);
+    if proof.queries.iter().map(|query| query.id).ne(ids) {
+        return Err(VerifierError::ChallengeMismatch);
+    }
+    for query in &proof.queries {
+        for opening in &query.trace_frame {
+            channel.send(&field_elements_to_bytes(&opening.row));
+        }
+        for opening in &query.fri_layers {
+            channel.send(&extension_field_elements_to_bytes(&opening.values));
+        }
+    }
+    channel.send(&proof.trace_proof.to_bytes());
+    for fri_proof in &proof.fri_proofs {
+        channel.send(&fri_proof.to_bytes());
+    }
+
+    // Prover Decommitted on a Set of Queries
+    // The openings of all the queries are first checked against the commitments with one multi-proof per tree
+    check_merkle_proofs::<H>(
+        eval_domain_size,
+        &folding_factors,
+        &proof.trace_cap,
+        &fri_polys_merkle_caps,
+        row_offset,
+        frame_size,
+        &proof.queries,
+        &proof.trace_proof,
+        &proof.fri_proofs,
+    )?;
 
-        check_decommittment_on_query(
-            &eval_domain,
-            f_merkle_root,
+    // We verify the data's consistency for each Query
+    // The last FRI layer of each query is evaluated from the remainder, whose degree is bounded by its number of
+    // coefficients. We process the verifications in parallel
+    let fri_remainder = DensePolynomial::from_coefficients_slice(&proof.fri_remainder);
+    (0..num_queries).into_par_iter().try_for_each(|query| {
+        let opening = &proof.queries[query];
+        check_decommittment_on_fri_layers(
+            w,
+            eval_domain_size,
             &betas,
-            &fri_polys_merkle_roots,
-            id,
-            f_id,
-            &authentication_path_f_id,
-            f_g_id,
-            &authentication_path_f_g_id,
-            f_g2_id,
-            &authentication_path_f_g2_id,
-            &fri_poly_id,
-            &authentication_path_fri_poly_id,
-            &fri_poly_sibling,
-            &authentication_path_fri_poly_sibling,
+            &folding_factors,
+            query,
+            opening.id,
+            &opening.fri_layers,
+            &fri_remainder,
+        )?;
+
+        // The decommitted CP value must be the combination of the constraints evaluated on the decommitted trace
+        let trace_frame: Vec<Vec<MyField>> = opening
+            .trace_frame
+            .iter()
+            .map(|trace_opening| trace_opening.row.clone())
+            .collect();
+        let x = coset_element(w, eval_domain_size, opening.id);
+        let CP_value = opening.fri_layers.first().map_or_else(
+            || fri_remainder.evaluate(&MyFieldExt::from_base_prime_field(x)),
+            |fri_opening| {
+                fri_opened_value(
+                    opening.id,
+                    eval_domain_size,
+                    folding_factors[0],
+                    fri_opening,
+                )
+            },
         );
-    });
+        if air.evaluate_composition(x, &trace_frame, &alphas) != CP_value {
+            return Err(VerifierError::ConstraintMismatch { query });
+        }
+        Ok(())
+    })?;
 
-    println!("Verification took: {:?}", start.elapsed());
-    true
+    info!("Verification took: {:?}", start.elapsed());
+    Ok(())
 }
@@ -0,0 +1,198 @@
+use ark_ff::{AdditiveGroup, FftField, Field};
+use ark_std::{test_rng, UniformRand};
+use stark101::air::{
+    fibonacci_sq::{FibonacciSq, FibonacciSqPublicInputs},
+    Air, BoundaryConstraint,
+};
+use stark101::common::{
+    finite_fields::{MyField, MyFieldExt},
+    polynomials::coset,
+};
+use std::iter::zip;
+
+#[test]
+fn test_fibonacci_sq_trace() {
+    let air = FibonacciSq::default();
+    let trace = air.generate_trace();
+    assert_eq!(trace.len(), 1, "trace must have a single column");
+    assert_eq!(
+        trace[0].len(),
+        1023,
+        "The trace must consist of exactly 1023 elements."
+    );
+    assert_eq!(
+        trace[0][1022],
+        MyField::from(2338775057u64),
+        "Wrong last element!"
+    );
+    assert!(air.is_valid_trace(&trace), "trace must be valid");
+}
+
+#[test]
+fn test_fibonacci_sq_other_instance() {
+    let public_inputs = FibonacciSqPublicInputs {
+        first_element: MyField::from(2),
+        second_element: MyField::from(3),
+        claimed_output: MyField::from(13), // 2, 3, 13
+        num_steps: 3,
+    };
+    let air = FibonacciSq::new(public_inputs);
+    let trace = air.generate_trace();
+    assert_eq!(
+        trace,
+        vec![vec![MyField::from(2), MyField::from(3), MyField::from(13)]]
+    );
+    assert!(air.is_valid_trace(&trace), "trace must be valid");
+    let wrong_air = FibonacciSq::new(FibonacciSqPublicInputs {
+        claimed_output: MyField::from(14),
+        ..public_inputs
+    });
+    assert!(
+        !wrong_air.is_valid_trace(&trace),
+        "trace must not reach a wrong claimed output"
+    );
+}
+
+#[test]
+fn test_fibonacci_sq_too_few_steps() {
+    let public_inputs = FibonacciSqPublicInputs {
+        num_steps: 2,
+        ..Default::default()
+    };
+    assert_eq!(
+        FibonacciSq::try_new(public_inputs),
+        Err("FibonacciSq must run for at least 3 steps".to_string())
+    );
+}
+
+#[test]
+fn test_invalid_trace() {
+    let air = FibonacciSq::default();
+    let mut trace = air.generate_trace();
+    trace[0][500] += MyField::ONE;
+    assert!(
+        !air.is_valid_trace(&trace),
+        "trace breaking the transition constraint must be invalid"
+    );
+    let mut trace = air.generate_trace();
+    trace[0].truncate(1022);
+    assert!(
+        !air.is_valid_trace(&trace),
+        "trace of wrong length must be invalid"
+    );
+}
+
+#[test]
+fn test_fibonacci_sq_constraints() {
+    let air = FibonacciSq::default();
+    assert_eq!(
+        air.boundary_constraints(),
+        vec![
+            BoundaryConstraint {
+                column: 0,
+                step: 0,
+                value: MyField::ONE
+            },
+            BoundaryConstraint {
+                column: 0,
+                step: 1,
+                value: MyField::from(3141592)
+            },
+            BoundaryConstraint {
+                column: 0,
+                step: 1022,
+                value: MyField::from(2338775057u64)
+            }
+        ]
+    );
+    assert_eq!(air.num_constraints(), 4, "FibonacciSq has 4 constraints");
+    assert_eq!(
+        air.transition_steps(),
+        1021,
+        "transition holds on 1021 steps"
+    );
+    assert_eq!(air.trace_domain_size(), 1024, "trace domain size is wrong");
+    assert_eq!(
+        air.composition_degree_bound(),
+        1024,
+        "The degree of CP must be less than 1024"
+    );
+    let g = air.trace_generator();
+    assert_eq!(
+        g.pow([1024]),
+        MyField::ONE,
+        "The generator g is of wrong order"
+    );
+    assert_ne!(
+        g.pow([512]),
+        MyField::ONE,
+        "The generator g is of wrong order"
+    );
+}
+
+#[test]
+fn test_fibonacci_sq_composition() {
+    // The composition of the FibonacciSq AIR must match the explicit formula
+    // CP(x) = alpha0 * p0(x) + alpha1 * p1(x) + alpha2 * p2(x) + alpha3 * p3(x) with
+    // p0(x) = (f(x) - 1) / (x - 1)
+    // p1(x) = (f(x) - 3141592) / (x - g)
+    // p2(x) = (f(x) - 2338775057) / (x - g^1022)
+    // p3(x) = (f(g^2x) - f(gx)^2 - f(x)^2) / ((x^1024 - 1) / ((x - g^1021) * (x - g^1022) * (x - g^1023)))
+    let air = FibonacciSq::default();
+    let g = air.trace_generator();
+    let rng = &mut test_rng();
+    for _ in 0..10 {
+        let x = MyField::rand(rng);
+        let (f_x, f_gx, f_g2x) = (MyField::rand(rng), MyField::rand(rng), MyField::rand(rng));
+        let alphas: Vec<MyFieldExt> = (0..4).map(|_| MyFieldExt::rand(rng)).collect();
+        let p0 = (f_x - MyField::ONE) / (x - MyField::ONE);
+        let p1 = (f_x - MyField::from(3141592)) / (x - g);
+        let p2 = (f_x - MyField::from(2338775057_u32)) / (x - g.pow([1022]));
+        let p3 = (f_g2x - f_gx.square() - f_x.square())
+            / ((x.pow([1024]) - MyField::ONE)
+                / ((x - g.pow([1021])) * (x - g.pow([1022])) * (x - g.pow([1023]))));
+        let frame = vec![vec![f_x], vec![f_gx], vec![f_g2x]];
+        let [p0, p1, p2, p3] = [p0, p1, p2, p3].map(MyFieldExt::from_base_prime_field);
+        assert_eq!(
+            air.evaluate_composition(x, &frame, &alphas),
+            alphas[0] * p0 + alphas[1] * p1 + alphas[2] * p2 + alphas[3] * p3,
+            "composition of FibonacciSq is wrong"
+        );
+    }
+    // Transition constraint vanishes on the trace
+    let trace = air.generate_trace();
+    assert_eq!(
+        air.evaluate_transition(&air.get_frame(&trace, 1020)),
+        vec![MyField::ZERO]
+    );
+}
+
+#[test]
+fn test_composition_divisors_on_coset() {
+    // With 1025 steps, the transition zerofier excludes 1025 of the 2048 points of the trace domain
+    let air = FibonacciSq::new(FibonacciSqPublicInputs {
+        num_steps: 1025,
+        ..FibonacciSqPublicInputs::default()
+    });
+    let eval_domain = coset(MyField::GENERATOR, 8 * air.trace_domain_size());
+    let divisors = air.composition_divisors_on_coset(&eval_domain);
+    assert_eq!(divisors.transition_inverses.len(), eval_domain.len());
+    let (x, g) = (eval_domain[1], air.trace_generator());
+    let denom: MyField = (1023..2048).map(|k| x - g.pow([k])).product();
+    assert_eq!(
+        divisors.transition_inverses[1],
+        denom / (x.pow([2048]) - MyField::ONE)
+    );
+    for i in (0..eval_domain.len()).step_by(97) {
+        let divisors_at = air.composition_divisors_at(eval_domain[i]);
+        assert_eq!(
+            divisors.transition_inverses[i],
+            divisors_at.transition_inverses[0]
+        );
+        for (inverses, inverses_at) in
+            zip(&divisors.boundary_inverses, &divisors_at.boundary_inverses)
+        {
+            assert_eq!(inverses[i], inverses_at[0]);
+        }
+    }
+}
@@ -1,10 +1,9 @@
-use ark_ff::{BigInteger, PrimeField};
 use hex::encode;
 use stark101::common::{
     channel::*,
-    finite_fields::MyField,
-    merkle::{create_merkle_tree, get_authentication_path},
+    finite_fields::{MyField, MyFieldExt},
 };
+use stark101::verifier::VerifierError;
 
 #[test]
 fn test_new_channel() {
@@ -14,77 +13,117 @@ fn test_new_channel() {
         "0000000000000000000000000000000000000000000000000000000000000000",
         "state must be composed of 0 bytes"
     );
-    assert_eq!(channel.proof, vec![], "proof must be empty vector");
 }
 
 #[test]
 fn test_send() {
     let mut channel = Channel::new();
-    let data = [01u8; 32];
+    let data = [1u8; 32];
     channel.send(&data.to_vec());
     assert_eq!(
         encode(channel.state),
-        "5c85955f709283ecce2b74f1b1552918819f390911816e7bb466805a38ab87f3",
+        "4fbcd862232fc131a874fd29d738e5d318318d799b44da38ac397fc3e2f89d5f",
         "state is wrong"
     );
-    assert_eq!(
-        channel.proof,
-        vec![Member::new(Type::Send, data.to_vec())],
-        "proof is wrong"
-    );
 }
 
 #[test]
 fn test_receive_random_field_elements() {
     let mut channel = Channel::new();
     // we first send some data otherwise if we receive directly random field element it will be equal to 0
-    let data = [01u8; 32];
+    let data = [1u8; 32];
     channel.send(&data.to_vec());
     // we receive a first random field element
-    let _ = channel.receive_random_field_element();
+    let field_element = channel.receive_random_field_element();
     assert_eq!(
         encode(channel.state),
-        "705ede9d42476fc3e5a978b042ce790a193678f46d19f47ec4ab46539c47b76d",
+        "54b97412cf57ce5107a70fde32bfe0e4b46d2d8e89c5378c9bf8970fe82ccdf2",
         "state is wrong"
     );
-    assert_eq!(channel.proof.len(), 2, "proof should contain 2 members");
     assert_eq!(
-        channel.proof[1],
-        Member::new(
-            Type::Receive,
-            MyField::from(2014382809_u64)
-                .into_bigint()
-                .to_bytes_le()
-                .to_vec()
-        ),
+        field_element,
+        MyField::from(1680861010_u64),
         "first received field element is wrong"
     );
     // we receive a second random field element
-    let _ = channel.receive_random_field_element();
+    let field_element = channel.receive_random_field_element();
     assert_eq!(
         encode(channel.state),
-        "f6bca4ad35bf0e47f352f618eebbb6beb4d5398706ce39156e8f4c9fd8f50a46",
+        "f5d47ec1227494771f81d09784f18dcfb642b6e5911d820e5fd74073d556bdda",
         "state is wrong"
     );
-    assert_eq!(channel.proof.len(), 3, "proof should contain 3 members");
     assert_eq!(
-        channel.proof[2],
-        Member::new(
-            Type::Receive,
-            MyField::from(2889731371_u64)
-                .into_bigint()
-                .to_bytes_le()
-                .to_vec()
-        ),
+        field_element,
+        MyField::from(2793381913_u64),
         "second received field element is wrong"
     );
 }
 
+#[test]
+fn test_receive_random_extension_field_element() {
+    let mut channel = Channel::new();
+    let data = [1u8; 32];
+    channel.send(&data.to_vec());
+    // The three coefficients are drawn from one state update
+    let element = channel.receive_random_extension_field_element();
+    assert_eq!(
+        element,
+        MyFieldExt::new(
+            MyField::from(1680861010_u64),
+            MyField::from(544371226_u64),
+            MyField::from(1383761571_u64)
+        )
+    );
+    assert_eq!(
+        encode(channel.state),
+        "54b97412cf57ce5107a70fde32bfe0e4b46d2d8e89c5378c9bf8970fe82ccdf2",
+        "state must be updated once"
+    );
+}
+
+#[test]
+fn test_receive_random_extension_field_elements() {
+    let mut channel = Channel::new();
+    let data = [1u8; 32];
+    channel.send(&data.to_vec());
+    let _ = channel.receive_random_field_element();
+    // A vector of elements is drawn from one state update
+    let elements = channel.receive_random_extension_field_elements(2);
+    assert_eq!(
+        elements,
+        vec![
+            MyFieldExt::new(
+                MyField::from(2793381913_u64),
+                MyField::from(1186428009_u64),
+                MyField::from(2933650006_u64)
+            ),
+            MyFieldExt::new(
+                MyField::from(1773688610_u64),
+                MyField::from(19603053_u64),
+                MyField::from(2684312855_u64)
+            ),
+        ]
+    );
+    assert_eq!(
+        encode(channel.state),
+        "f5d47ec1227494771f81d09784f18dcfb642b6e5911d820e5fd74073d556bdda",
+        "state must be updated once"
+    );
+}
+
+#[test]
+fn test_receive_random_integers_in_degenerate_ranges() {
+    let mut channel = Channel::new();
+    channel.send(&[1u8; 32].to_vec());
+    assert_eq!(channel.receive_random_int(5, 5), 5);
+    let _ = channel.receive_random_int(0, u64::MAX);
+}
+
 #[test]
 fn test_receive_random_integers() {
     let mut channel = Channel::new();
     // we first send some data otherwise if we receive directly random integer it will be equal to 0
-    let data = [01u8; 32];
+    let data = [1u8; 32];
     channel.send(&data.to_vec());
     // we receive a first random int
     let (min, max) = (0, 8191);
@@ -93,105 +132,102 @@ fn test_receive_random_integers() {
     assert!(int0 <= max, "random int must less than max");
     assert_eq!(
         encode(channel.state),
-        "705ede9d42476fc3e5a978b042ce790a193678f46d19f47ec4ab46539c47b76d",
+        "54b97412cf57ce5107a70fde32bfe0e4b46d2d8e89c5378c9bf8970fe82ccdf2",
         "state is wrong"
     );
-    assert_eq!(channel.proof.len(), 2, "proof should contain 2 members");
-    assert_eq!(
-        channel.proof[1],
-        Member::new(Type::Receive, 2035_u64.to_le_bytes().to_vec()),
-        "first received int is wrong"
-    );
+    assert_eq!(int0, 7481, "first received int is wrong");
     // we receive a second random int
     let int1 = channel.receive_random_int(min, max);
     assert!(int1 >= min, "random int must greater than min");
     assert!(int1 <= max, "random int must less than max");
     assert_eq!(
         encode(channel.state),
-        "f6bca4ad35bf0e47f352f618eebbb6beb4d5398706ce39156e8f4c9fd8f50a46",
+        "f5d47ec1227494771f81d09784f18dcfb642b6e5911d820e5fd74073d556bdda",
         "state is wrong"
     );
-    assert_eq!(channel.proof.len(), 3, "proof should contain 3 members");
-    assert_eq!(
-        channel.proof[2],
-        Member::new(Type::Receive, 5997_u64.to_le_bytes().to_vec()),
-        "second received int is wrong"
-    );
-}
-
-#[test]
-fn test_parse_sent_root() {
-    let data = vec![
-        MyField::from(1),
-        MyField::from(2),
-        MyField::from(3),
-        MyField::from(4),
-    ];
-    let merkle_tree = create_merkle_tree(&data);
-    let root = merkle_tree.root().unwrap();
-    let mut channel = Channel::new();
-    channel.send(&root.to_vec());
-    let parsed_root = parse_sent_root(&channel.proof[0]);
-    assert_eq!(root, parsed_root, "parsed root is wrong");
+    assert_eq!(int1, 3219, "second received int is wrong");
 }
 
 #[test]
-fn test_parse_received_field_element() {
+fn test_receive_random_distinct_ints() {
     let mut channel = Channel::new();
-    // we first send some data otherwise if we receive directly random field element it will be equal to 0
-    let data = [01u8; 32];
-    channel.send(&data.to_vec());
-    let field_element = channel.receive_random_field_element();
-    let parsed_field_element = parse_received_field_element(&channel.proof[1]);
-    assert_eq!(
-        field_element, parsed_field_element,
-        "parsed field element is wrong"
-    );
+    channel.send(&[1u8; 32].to_vec());
+    // Drawing as many integers as the size of the range yields a permutation of the range
+    let mut ints = channel.receive_random_distinct_ints(8, 8);
+    ints.sort_unstable();
+    assert_eq!(ints, (0..8).collect::<Vec<u64>>());
+    // Query ids cover the whole eval domain
+    let ids = channel.receive_random_query_ids(3, 8192);
+    assert_eq!(ids.len(), 3);
+    assert!(ids.iter().all(|&id| id < 8192));
+    assert!(ids[0] != ids[1] && ids[0] != ids[2] && ids[1] != ids[2]);
 }
 
 #[test]
-fn test_parse_sent_field_element() {
+fn test_grind() {
     let mut channel = Channel::new();
-    let field_element = MyField::from(1234);
-    channel.send(&field_element.into_bigint().to_bytes_le());
-    let parsed_field_element = parse_sent_field_element(&channel.proof[0]);
-    assert_eq!(
-        field_element, parsed_field_element,
-        "parsed field element is wrong"
-    );
+    channel.send(&[1u8; 32].to_vec());
+    let mut verifier_channel = channel.clone();
+    let mut expected_channel = channel.clone();
+    let nonce = channel.grind(8);
+    // The nonce is sent as 8 little-endian bytes
+    expected_channel.send(&nonce.to_le_bytes().to_vec());
+    assert_eq!(channel, expected_channel);
+    // The verifier accepts the nonce and absorbs it, but rejects any smaller one
+    if nonce > 0 {
+        assert_eq!(
+            verifier_channel.clone().replay_proof_of_work(nonce - 1, 8),
+            Err(VerifierError::ProofOfWorkMismatch)
+        );
+    }
+    assert_eq!(verifier_channel.replay_proof_of_work(nonce, 8), Ok(()));
+    assert_eq!(verifier_channel, channel);
+    // Without grinding, the first nonce is accepted
+    assert_eq!(Channel::new().grind(0), 0);
 }
 
 #[test]
-fn test_parse_received_int() {
+fn test_grind_finds_smallest_nonce() {
     let mut channel = Channel::new();
-    // we first send some data otherwise if we receive directly random field element it will be equal to 0
-    let data = [01u8; 32];
-    channel.send(&data.to_vec());
-    let int = channel.receive_random_int(0, 8191);
-    let parsed_int = parse_received_int(&channel.proof[1]);
-    assert_eq!(int, parsed_int, "parsed int is wrong");
+    channel.send(&[1u8; 32].to_vec());
+    let verifier_channel = channel.clone();
+    // The nonce lies past the first batch of nonces searched in parallel, and no smaller nonce solves the proof-of-work
+    let nonce = channel.grind(14);
+    assert!(nonce >= 1 << 12);
+    assert!((0..nonce).all(|smaller_nonce| {
+        verifier_channel
+            .clone()
+            .replay_proof_of_work(smaller_nonce, 14)
+            .is_err()
+    }));
 }
 
 #[test]
-fn test_parse_sent_authentication_path() {
-    let data = vec![
-        MyField::from(1),
-        MyField::from(2),
-        MyField::from(3),
-        MyField::from(4),
-    ];
-    let merkle_tree = create_merkle_tree(&data);
-    let mut channel = Channel::new();
-    let authentication_path = get_authentication_path(&merkle_tree, 3);
-    channel.send(
-        &authentication_path
-            .iter()
-            .flat_map(|arr| arr.to_vec())
-            .collect(),
+fn test_replay_channel() {
+    let mut prover_channel = Channel::new();
+    let data = [1u8; 32];
+    prover_channel.send(&data.to_vec());
+    let field_element = prover_channel.receive_random_field_element();
+    let int = prover_channel.receive_random_int(0, 8191);
+    // the verifier re-derives the same challenges from the same messages
+    let mut verifier_channel = Channel::new();
+    verifier_channel.send(&data.to_vec());
+    assert_eq!(
+        verifier_channel.receive_random_field_element(),
+        field_element,
+        "replayed field element is wrong"
+    );
+    assert_eq!(
+        verifier_channel.receive_random_int(0, 8191),
+        int,
+        "replayed int is wrong"
     );
-    let parsed_authentication_path = parse_sent_authentication_path(&channel.proof[0]);
     assert_eq!(
-        authentication_path, parsed_authentication_path,
-        "parsed authentication path is wrong"
+        verifier_channel, prover_channel,
+        "replayed channel must match the prover's channel"
     );
+    // another message yields other challenges
+    let mut forged_channel = Channel::new();
+    forged_channel.send(&[2u8; 32].to_vec());
+    assert_ne!(forged_channel.receive_random_field_element(), field_element);
 }
@@ -0,0 +1,102 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Runs the verifier on a proof given on stdin and returns whether it succeeded
+fn verify(proof: &[u8], args: &[&str]) -> bool {
+    let mut verifier = Command::new(env!("CARGO_BIN_EXE_verifier"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to run verifier");
+    verifier.stdin.take().unwrap().write_all(proof).unwrap();
+    verifier.wait().unwrap().success()
+}
+
+#[test]
+fn test_prove_and_verify_through_pipe() {
+    let output = Command::new(env!("CARGO_BIN_EXE_prover"))
+        .args(["-q", "--proof", "-", "--format", "binary", "fibonacci-sq"])
+        .args(["--second-element", "5", "--num-steps", "64"])
+        .output()
+        .expect("Failed to run prover");
+    assert!(output.status.success(), "prover must succeed");
+    assert!(output.stderr.is_empty(), "quiet prover must not log");
+    let proof = output.stdout;
+    // a[63] of the sequence starting with 1, 5
+    let statement = [
+        "fibonacci-sq",
+        "--second-element",
+        "5",
+        "--num-steps",
+        "64",
+        "--claimed-output",
+    ];
+    assert!(
+        verify(
+            &proof,
+            &[
+                &statement[..],
+                &["480567251", "--proof", "-", "--format", "binary"]
+            ]
+            .concat()
+        ),
+        "verifier must accept the proof"
+    );
+    assert!(
+        !verify(
+            &proof,
+            &[
+                &statement[..],
+                &["480567252", "--proof", "-", "--format", "binary"]
+            ]
+            .concat()
+        ),
+        "verifier must exit with a failure on a wrong claimed output"
+    );
+    assert!(
+        !verify(
+            &proof,
+            &[
+                &statement[..],
+                &["480567251", "--proof", "-", "--format", "json"]
+            ]
+            .concat()
+        ),
+        "verifier must exit with a failure on a malformed proof"
+    );
+}
+
+#[test]
+fn test_reject_invalid_arguments() {
+    for args in [
+        &["--folding-factor", "3"][..],
+        &["--num-queries", "100000"],
+        &["--blowup", "2097152"],
+        &["fibonacci-sq", "--num-steps", "2"],
+    ] {
+        for program in [env!("CARGO_BIN_EXE_prover"), env!("CARGO_BIN_EXE_verifier")] {
+            let output = Command::new(program)
+                .args(["--proof", "-"])
+                .args(args)
+                .stdin(Stdio::null())
+                .output()
+                .expect("Failed to run program");
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(
+                !output.status.success(),
+                "{} must fail with {:?}",
+                program,
+                args
+            );
+            assert!(
+                stderr.contains("Invalid") && !stderr.contains("panicked"),
+                "{} must report {:?} as invalid instead of panicking: {}",
+                program,
+                args,
+                stderr
+            );
+        }
+    }
+}
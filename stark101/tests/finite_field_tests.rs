@@ -1,6 +1,6 @@
 use ark_ff::{fields::Field, AdditiveGroup};
 use ark_std::{test_rng, UniformRand};
-use stark101::common::finite_fields::MyField;
+use stark101::common::finite_fields::{MyField, MyFieldExt};
 
 #[test]
 fn test_field_modulus() {
@@ -59,3 +59,45 @@ fn test_field_division() {
         assert_eq!(inverse_t * t, MyField::ONE, "Inverse computation failed");
     }
 }
+
+#[test]
+fn test_extension_field() {
+    // X^3 = 5 in the extension
+    let x = MyFieldExt::new(MyField::ZERO, MyField::ONE, MyField::ZERO);
+    assert_eq!(x.pow([3]), MyFieldExt::from(5));
+    assert_eq!(MyFieldExt::extension_degree(), 3);
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let t = MyFieldExt::rand(&mut rng);
+        assert_eq!(
+            t.inverse().unwrap() * t,
+            MyFieldExt::ONE,
+            "Inverse computation failed"
+        );
+        // The Frobenius map is x -> x^p and fixes the base field
+        let mut frobenius = t;
+        frobenius.frobenius_map_in_place(1);
+        assert_eq!(frobenius, t.pow([3221225473u64]), "Frobenius map is wrong");
+        let mut base_element = MyFieldExt::from_base_prime_field(MyField::rand(&mut rng));
+        let expected = base_element;
+        base_element.frobenius_map_in_place(2);
+        assert_eq!(base_element, expected);
+    }
+}
+
+#[test]
+fn test_extension_field_sqrt() {
+    let mut rng = test_rng();
+    for _ in 0..10 {
+        let t = MyFieldExt::rand(&mut rng);
+        let square_root = t.square().sqrt().unwrap();
+        assert!(
+            square_root == t || square_root == -t,
+            "Square root is wrong"
+        );
+    }
+    assert!(
+        MyFieldExt::from(5).sqrt().is_none(),
+        "5 must not be a square"
+    );
+}
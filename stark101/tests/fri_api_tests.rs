@@ -0,0 +1,452 @@
+use ark_ff::{AdditiveGroup, FftField, Field};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_std::test_rng;
+use stark101::common::{
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::{
+        batch_fri_evaluations, compute_next_fri_layer, create_fri_merkle_tree,
+        decommit_batched_merkle_proofs, decommit_on_batched_query, fri_folding_factors,
+        fri_inverse_twiddles, fri_leaf_size, fri_opened_value, CommittedEvaluations,
+    },
+    hasher::{Hasher, Keccak256, Sha256},
+    merkle::{get_merkle_cap, MerkleTree},
+    options::{HashFunction, ProofOptions},
+    polynomials::{coset, interpolate_extension_on_coset},
+    proof::{extension_field_elements_to_bytes, FriProof, MerkleCap},
+};
+use stark101::prover::fri::FriProver;
+use stark101::verifier::{fri::FriVerifier, VerifierError};
+use std::iter::zip;
+
+// Evaluations of a random polynomial of degree less than degree_bound on the coset offset * H of size blowup times
+// degree_bound
+fn evaluations(offset: MyField, degree_bound: usize, blowup: usize) -> Vec<MyFieldExt> {
+    let poly = DensePolynomial::<MyFieldExt>::rand(degree_bound - 1, &mut test_rng());
+    coset(offset, blowup * degree_bound)
+        .iter()
+        .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+        .collect()
+}
+
+fn prove<H: Hasher>(
+    offset: MyField,
+    evaluations: Vec<MyFieldExt>,
+    degree_bound: usize,
+    options: &ProofOptions,
+) -> FriProof {
+    FriProver::<H>::new(offset, evaluations, degree_bound, options).prove(&mut Channel::default())
+}
+
+fn verify<H: Hasher>(
+    offset: MyField,
+    degree_bound: usize,
+    proof: &FriProof,
+    options: &ProofOptions,
+) -> Result<(), VerifierError> {
+    FriVerifier::new(offset, degree_bound, options).verify(
+        proof.batched_caps.len(),
+        proof,
+        &mut Channel::<H>::default(),
+    )
+}
+
+// Runs the steps of FriProver::prove on evaluations which may be of too high degree, letting tamper change each folded
+// layer before it is committed, and sends the coefficients of the last layer truncated to max_remainder_degree + 1
+// instead of panicking
+fn prove_dishonestly(
+    offset: MyField,
+    evaluations: Vec<Vec<MyFieldExt>>,
+    degree_bound: usize,
+    options: &ProofOptions,
+    tamper: impl Fn(usize, &mut Vec<MyFieldExt>),
+) -> FriProof {
+    let domain = coset(offset, options.blowup * degree_bound);
+    let folding_factors = fri_folding_factors(
+        degree_bound,
+        options.folding_factor,
+        options.max_remainder_degree + 1,
+    );
+    let merkles: Vec<MerkleTree<Sha256>> = evaluations
+        .iter()
+        .map(|evaluation| create_fri_merkle_tree(evaluation, folding_factors[0]))
+        .collect();
+    let batched_caps: Vec<MerkleCap> = merkles
+        .iter()
+        .map(|merkle| MerkleCap(get_merkle_cap(merkle, options.cap_height)))
+        .collect();
+    let committed: Vec<CommittedEvaluations<Sha256>> = zip(&evaluations, &merkles)
+        .map(|(evaluation, merkle)| (evaluation.as_slice(), merkle))
+        .collect();
+    let mut channel = Channel::<Sha256>::new();
+    for cap in &batched_caps {
+        channel.send(&cap.to_bytes());
+    }
+    let coefficients = channel.receive_random_extension_field_elements(evaluations.len());
+    let inverse_twiddles = fri_inverse_twiddles(&domain);
+    let mut fri_layers = vec![batch_fri_evaluations(&evaluations, &coefficients)];
+    let mut fri_merkles = vec![];
+    for (layer, &folding_factor) in folding_factors.iter().enumerate() {
+        let beta = channel.receive_random_extension_field_element();
+        let mut next_layer = compute_next_fri_layer(
+            fri_layers.last().unwrap(),
+            &inverse_twiddles,
+            beta,
+            folding_factor,
+        );
+        tamper(layer + 1, &mut next_layer);
+        if layer + 1 < folding_factors.len() {
+            fri_merkles.push(create_fri_merkle_tree(
+                &next_layer,
+                fri_leaf_size(&folding_factors, layer + 1),
+            ));
+            channel.send(
+                &MerkleCap(get_merkle_cap(
+                    fri_merkles.last().unwrap(),
+                    options.cap_height,
+                ))
+                .to_bytes(),
+            );
+        }
+        fri_layers.push(next_layer);
+    }
+    let last_layer = fri_layers.last().unwrap();
+    let last_offset = offset.pow([(domain.len() / last_layer.len()) as u64]);
+    let mut fri_remainder = interpolate_extension_on_coset(last_offset, last_layer).coeffs;
+    fri_remainder.resize(options.max_remainder_degree + 1, MyFieldExt::ZERO);
+    channel.send(&extension_field_elements_to_bytes(&fri_remainder));
+    let pow_nonce = channel.grind(options.grinding_bits);
+    let ids = channel.receive_random_query_ids(options.num_queries, domain.len());
+    let queries = ids
+        .iter()
+        .map(|&id| decommit_on_batched_query(id, &committed, &fri_layers, &mut channel))
+        .collect();
+    let (batched_proofs, fri_proofs) = decommit_batched_merkle_proofs(
+        &ids,
+        &committed,
+        &fri_merkles,
+        options.cap_height,
+        &mut channel,
+    );
+    FriProof {
+        batched_caps,
+        fri_caps: fri_merkles
+            .iter()
+            .map(|merkle| MerkleCap(get_merkle_cap(merkle, options.cap_height)))
+            .collect(),
+        fri_remainder,
+        pow_nonce,
+        queries,
+        batched_proofs,
+        fri_proofs,
+    }
+}
+
+#[test]
+fn test_prove_and_verify_low_degree() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    let evaluations = evaluations(offset, 64, options.blowup);
+    let prover = FriProver::<Sha256>::new(offset, evaluations.clone(), 64, &options);
+    let commitments = prover.commitments();
+    let proof = prover.prove(&mut Channel::new());
+    assert_eq!(proof.batched_caps, commitments);
+    // The first FRI layer is the combination of the functions, the last one is sent as the remainder
+    assert_eq!(proof.fri_caps.len(), 5);
+    assert_eq!(proof.fri_proofs.len(), 5);
+    assert!(
+        verify::<Sha256>(offset, 64, &proof, &options).is_ok(),
+        "honest FRI proof must be accepted"
+    );
+    // The queries open the committed evaluations in their first layer
+    for query in &proof.queries {
+        assert_eq!(
+            fri_opened_value(query.id, 512, 2, &query.batched[0]),
+            evaluations[query.id]
+        );
+    }
+}
+
+#[test]
+fn test_prove_and_verify_low_degree_with_options() {
+    let offset = MyField::from(7);
+    for options in [
+        ProofOptions::new(4, 5, 4, 4, HashFunction::Sha256, 1, 7),
+        ProofOptions::new(2, 8, 16, 0, HashFunction::Sha256, 2, 0),
+    ] {
+        let evaluations = evaluations(offset, 256, options.blowup);
+        let proof = prove::<Sha256>(offset, evaluations, 256, &options);
+        assert_eq!(proof.fri_remainder.len(), options.max_remainder_degree + 1);
+        assert!(
+            verify::<Sha256>(offset, 256, &proof, &options).is_ok(),
+            "honest FRI proof must be accepted with {:?}",
+            options
+        );
+    }
+}
+
+#[test]
+fn test_hash_function_of_options() {
+    // The hash function of the options is the one of the channel
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(8, 3, 2, 0, HashFunction::Keccak256, 0, 0);
+    let proof = prove::<Keccak256>(offset, evaluations(offset, 64, 8), 64, &options);
+    assert!(verify::<Keccak256>(offset, 64, &proof, &options).is_ok());
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::InvalidOptions(
+            "Hash function of the options must be the one of the channel".to_string()
+        ))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Hash function of the options must be the one of the channel")]
+fn test_prove_with_other_hash_function() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(8, 3, 2, 0, HashFunction::Keccak256, 0, 0);
+    prove::<Sha256>(offset, evaluations(offset, 64, 8), 64, &options);
+}
+
+#[test]
+fn test_reject_forged_fri_proof() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    let proof = prove::<Sha256>(offset, evaluations(offset, 64, 8), 64, &options);
+
+    let mut forged_proof = proof.clone();
+    forged_proof.queries[0].batched[0].values[0] += MyFieldExt::ONE;
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &forged_proof, &options),
+        Err(VerifierError::BatchedMerkleProofMismatch { function: 0 })
+    );
+    let mut forged_proof = proof.clone();
+    forged_proof.queries[0].fri_layers[0].values[0] += MyFieldExt::ONE;
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &forged_proof, &options),
+        Err(VerifierError::MerkleProofMismatch { layer: 1 })
+    );
+    let mut forged_proof = proof.clone();
+    forged_proof.fri_remainder[0] += MyFieldExt::ONE;
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &forged_proof, &options),
+        Err(VerifierError::ChallengeMismatch)
+    );
+    // The proof does not test a smaller degree bound
+    assert_eq!(
+        verify::<Sha256>(offset, 32, &proof, &options),
+        Err(VerifierError::MalformedProof(
+            "Proof must contain 4 FRI caps".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_reject_other_transcript() {
+    // FRI runs on the channel of the caller, so that it can follow other messages of a larger protocol
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    let mut channel = Channel::<Sha256>::new();
+    channel.send(&[1u8; 32].to_vec());
+    let proof =
+        FriProver::new(offset, evaluations(offset, 64, 8), 64, &options).prove(&mut channel);
+    let mut channel = Channel::<Sha256>::new();
+    channel.send(&[1u8; 32].to_vec());
+    assert!(FriVerifier::new(offset, 64, &options)
+        .verify(1, &proof, &mut channel)
+        .is_ok());
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_prove_and_verify_batch() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(4, 4, 4, 0, HashFunction::Sha256, 1, 3);
+    let batch: Vec<Vec<MyFieldExt>> = (0..3)
+        .map(|_| evaluations(offset, 64, options.blowup))
+        .collect();
+    let proof = FriProver::<Sha256>::new_batched(offset, batch.clone(), 64, &options)
+        .prove(&mut Channel::new());
+    assert_eq!(proof.batched_caps.len(), 3);
+    assert_eq!(proof.batched_proofs.len(), 3);
+    assert!(
+        verify::<Sha256>(offset, 64, &proof, &options).is_ok(),
+        "honest batched FRI proof must be accepted"
+    );
+    // Each function is opened at the queries
+    for query in &proof.queries {
+        for (evaluation, opening) in batch.iter().zip(&query.batched) {
+            assert_eq!(
+                fri_opened_value(query.id, 256, 4, opening),
+                evaluation[query.id]
+            );
+        }
+    }
+    let mut forged_proof = proof.clone();
+    forged_proof.queries[1].batched[2].values[3] += MyFieldExt::ONE;
+    assert_eq!(
+        verify::<Sha256>(offset, 64, &forged_proof, &options),
+        Err(VerifierError::BatchedMerkleProofMismatch { function: 2 })
+    );
+    // The proof is bound to the number of functions
+    assert_eq!(
+        FriVerifier::new(offset, 64, &options).verify(2, &proof, &mut Channel::<Sha256>::new()),
+        Err(VerifierError::MalformedProof(
+            "Proof must contain 2 batched caps".to_string()
+        ))
+    );
+}
+
+// Functions committed by the caller with the layout of the first FRI layer, with their trees
+fn commit(
+    batch: &[Vec<MyFieldExt>],
+    degree_bound: usize,
+    options: &ProofOptions,
+) -> Vec<MerkleTree<Sha256>> {
+    let folding_factors = fri_folding_factors(
+        degree_bound,
+        options.folding_factor,
+        options.max_remainder_degree + 1,
+    );
+    batch
+        .iter()
+        .map(|evaluation| create_fri_merkle_tree(evaluation, fri_leaf_size(&folding_factors, 0)))
+        .collect()
+}
+
+#[test]
+fn test_prove_and_verify_committed_batch() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(4, 4, 4, 0, HashFunction::Sha256, 1, 3);
+    let batch: Vec<Vec<MyFieldExt>> = (0..3)
+        .map(|_| evaluations(offset, 64, options.blowup))
+        .collect();
+    let merkles = commit(&batch, 64, &options);
+    let committed: Vec<CommittedEvaluations<Sha256>> = zip(&batch, &merkles)
+        .map(|(evaluation, merkle)| (evaluation.as_slice(), merkle))
+        .collect();
+    let prover = FriProver::new_committed(offset, &committed, 64, &options);
+    // The commitments are the caps of the caller's trees, and the proof is the one of the same functions committed by
+    // the prover
+    for (cap, merkle) in zip(prover.commitments(), &merkles) {
+        assert_eq!(cap.0, get_merkle_cap(merkle, options.cap_height));
+    }
+    let proof = prover.prove(&mut Channel::new());
+    assert_eq!(
+        proof,
+        FriProver::<Sha256>::new_batched(offset, batch.clone(), 64, &options)
+            .prove(&mut Channel::new())
+    );
+    assert!(verify::<Sha256>(offset, 64, &proof, &options).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "Expected trees of 64 leaves")]
+fn test_prove_committed_batch_with_wrong_leaf_size() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(4, 4, 4, 0, HashFunction::Sha256, 1, 3);
+    let evaluation = evaluations(offset, 64, options.blowup);
+    let merkle = create_fri_merkle_tree::<Sha256>(&evaluation, 2);
+    FriProver::new_committed(offset, &[(evaluation.as_slice(), &merkle)], 64, &options);
+}
+
+#[test]
+#[should_panic(expected = "Batched tree does not commit to the coset of the query in its leaf")]
+fn test_prove_committed_batch_with_other_tree() {
+    // The tree has the right shape but commits to another function
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::new(4, 4, 4, 0, HashFunction::Sha256, 1, 3);
+    let batch = vec![evaluations(offset, 64, 4), evaluations(offset, 32, 8)];
+    let merkles = commit(&batch, 64, &options);
+    FriProver::new_committed(offset, &[(batch[0].as_slice(), &merkles[1])], 64, &options)
+        .prove(&mut Channel::new());
+}
+
+#[test]
+#[should_panic(expected = "The degree of the remainder must be at most 0")]
+fn test_prove_batch_with_high_degree_function() {
+    // A single function of too high degree spoils the combination
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    let batch = vec![evaluations(offset, 64, 8), evaluations(offset, 128, 4)];
+    FriProver::<Sha256>::new_batched(offset, batch, 64, &options).prove(&mut Channel::new());
+}
+
+#[test]
+#[should_panic(expected = "The degree of the remainder must be at most 0")]
+fn test_prove_high_degree() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    // The evaluations have degree less than 128, not 64
+    prove::<Sha256>(offset, evaluations(offset, 128, 4), 64, &options);
+}
+
+#[test]
+fn test_reject_high_degree() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    // The evaluations have degree less than 128, not 64: the last layer has degree 1, and its constant remainder does
+    // not match it
+    let proof = prove_dishonestly(
+        offset,
+        vec![evaluations(offset, 128, 4)],
+        64,
+        &options,
+        |_, _| {},
+    );
+    assert!(matches!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::FriFoldingMismatch { .. })
+    ));
+    // Replacing the last layer, the 6th folded one, by a constant one makes the remainder match it, but not the folding
+    // of the layer before
+    let proof = prove_dishonestly(
+        offset,
+        vec![evaluations(offset, 128, 4)],
+        64,
+        &options,
+        |layer, values| {
+            if layer == 6 {
+                let value = values[0];
+                values.fill(value);
+            }
+        },
+    );
+    assert!(matches!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::FriFoldingMismatch { .. })
+    ));
+    // The same steps on evaluations of low degree give an honest proof
+    let evaluations = evaluations(offset, 64, options.blowup);
+    assert_eq!(
+        prove_dishonestly(offset, vec![evaluations.clone()], 64, &options, |_, _| {}),
+        prove::<Sha256>(offset, evaluations, 64, &options)
+    );
+}
+
+#[test]
+fn test_reject_batch_with_high_degree_function() {
+    let offset = MyField::GENERATOR;
+    let options = ProofOptions::default();
+    let batch = vec![evaluations(offset, 64, 8), evaluations(offset, 128, 4)];
+    let proof = prove_dishonestly(offset, batch.clone(), 64, &options, |_, _| {});
+    assert!(matches!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::FriFoldingMismatch { .. })
+    ));
+    // Committing to zero in place of the first folded layer makes all the next layers and the remainder vanish, but the
+    // verifier folds the combination of the batched cosets itself
+    let proof = prove_dishonestly(offset, batch, 64, &options, |layer, values| {
+        if layer == 1 {
+            values.fill(MyFieldExt::ZERO);
+        }
+    });
+    assert_eq!(proof.fri_remainder, vec![MyFieldExt::ZERO]);
+    assert!(matches!(
+        verify::<Sha256>(offset, 64, &proof, &options),
+        Err(VerifierError::FriFoldingMismatch { layer: 0, .. })
+    ));
+}
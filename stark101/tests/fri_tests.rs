@@ -1,21 +1,32 @@
+use std::iter::zip;
 use std::vec;
 
 use ark_ff::{BigInteger, FftField, Field, PrimeField};
-use ark_poly::{univariate::DensePolynomial, Polynomial};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
 use ark_std::{test_rng, UniformRand};
 use hex::decode;
 use stark101::common::{
-    channel::{Channel, Member, Type},
-    finite_fields::MyField,
+    channel::Channel,
+    finite_fields::{flatten_extension_field_elements, MyField, MyFieldExt},
     fri::*,
-    merkle::{create_merkle_tree, verify_decommitment},
+    hasher::Sha256,
+    merkle::{
+        create_merkle_tree, create_merkle_tree_from_columns, get_multi_proof,
+        verify_multi_decommitment,
+    },
+    proof::{extension_field_elements_to_bytes, FriLayerOpening},
 };
 
+// Element of the extension field from its coefficients
+fn ext(c0: u64, c1: u64, c2: u64) -> MyFieldExt {
+    MyFieldExt::new(MyField::from(c0), MyField::from(c1), MyField::from(c2))
+}
+
 #[test]
 fn test_next_fri_domain() {
     let rng = &mut test_rng();
     let fri_domain: Vec<MyField> = (0..8192).map(|_| MyField::rand(rng)).collect();
-    let next_fri_domain = compute_next_fri_domain(&fri_domain);
+    let next_fri_domain = compute_next_fri_domain(&fri_domain, 2);
     assert_eq!(
         next_fri_domain.len(),
         4096,
@@ -24,234 +35,337 @@ fn test_next_fri_domain() {
     for i in 0..4096 {
         assert_eq!(
             next_fri_domain[i],
-            fri_domain[i].pow(&(vec![2])),
+            fri_domain[i].pow([2]),
             "element number {} of next fri_domain is wrong",
             i
         );
     }
+    // Folding by 4 keeps a quarter of the domain, raised to the power 4
+    let next_fri_domain = compute_next_fri_domain(&fri_domain, 4);
+    assert_eq!(next_fri_domain.len(), 2048);
+    assert_eq!(next_fri_domain[5], fri_domain[5].pow([4]));
+}
+
+#[test]
+fn test_fri_folding_factors() {
+    assert_eq!(fri_folding_factors(1024, 2, 1), vec![2; 10]);
+    assert_eq!(fri_folding_factors(1024, 4, 1), vec![4; 5]);
+    // The last layer folds by the remaining ratio
+    assert_eq!(fri_folding_factors(1024, 8, 1), vec![8, 8, 8, 2]);
+    assert_eq!(fri_folding_factors(128, 16, 1), vec![16, 8]);
+    assert!(fri_folding_factors(1, 2, 1).is_empty());
+    // Folding stops at the degree bound of the remainder
+    assert_eq!(fri_folding_factors(1024, 2, 8), vec![2; 7]);
+    assert_eq!(fri_folding_factors(1024, 8, 16), vec![8, 8]);
+    assert_eq!(fri_folding_factors(1024, 16, 128), vec![8]);
+    assert!(fri_folding_factors(1024, 2, 1024).is_empty());
+    assert_eq!(fri_leaf_size(&[8, 8, 8, 2], 0), 8);
+    assert_eq!(fri_leaf_size(&[8, 8, 8, 2], 3), 2);
+    // CP has single element leaves when it is not folded at all
+    assert_eq!(fri_leaf_size(&[], 0), 1);
 }
 
 #[test]
 fn test_compute_next_fri_polynomial() {
     let poly = DensePolynomial {
         coeffs: vec![
-            MyField::ONE,
-            MyField::from(2),
-            MyField::from(3),
-            MyField::from(4),
+            MyFieldExt::ONE,
+            MyFieldExt::from(2),
+            MyFieldExt::from(3),
+            MyFieldExt::from(4),
         ],
     }; // 1 + 2*x + 3*x^2 + 4*x^3
-    let beta = MyField::from(5);
-    let next_poly = compute_next_fri_polynomial(&poly, beta);
+    let beta = MyFieldExt::from(5);
+    let next_poly = compute_next_fri_polynomial(&poly, beta, 2);
     let expected_next_poly = DensePolynomial {
-        coeffs: vec![MyField::from(11), MyField::from(23)],
+        coeffs: vec![MyFieldExt::from(11), MyFieldExt::from(23)],
     }; // 11 + 23*x
     assert_eq!(next_poly, expected_next_poly);
+    // Folding by 4 combines the 4 coefficients with the powers of beta
+    let next_poly = compute_next_fri_polynomial(&poly, beta, 4);
+    let expected_next_poly = DensePolynomial {
+        coeffs: vec![MyFieldExt::from(586)],
+    }; // 1 + 2*5 + 3*5^2 + 4*5^3
+    assert_eq!(next_poly, expected_next_poly);
 }
 
 #[test]
 fn test_compute_next_fri_layer() {
     let poly = DensePolynomial {
         coeffs: vec![
-            MyField::ONE,
-            MyField::from(2),
-            MyField::from(3),
-            MyField::from(4),
+            MyFieldExt::ONE,
+            MyFieldExt::from(2),
+            MyFieldExt::from(3),
+            MyFieldExt::from(4),
         ],
     }; // 1 + 2*x + 3*x^2 + 4*x^3
-    let beta = MyField::from(5);
-    let domain = vec![
-        MyField::ONE,
-        MyField::from(2),
-        MyField::from(3),
-        MyField::from(4),
-    ];
-    let (next_poly, next_domain, next_layer) = compute_next_fri_layer(&poly, &domain, beta);
-    let expected_next_poly = DensePolynomial {
-        coeffs: vec![MyField::from(11), MyField::from(23)],
-    }; // 11 + 23*x
-    let expected_next_domain = vec![MyField::from(1), MyField::from(4)];
-    let expected_next_layer = vec![MyField::from(34), MyField::from(103)];
-    assert_eq!(next_poly, expected_next_poly, "next_poly does not match");
-    assert_eq!(
-        next_domain, expected_next_domain,
-        "next_domain does not match"
-    );
+    let beta = MyFieldExt::from(5);
+    // Create a coset of a Group of size 4
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 4]);
+    let domain: Vec<MyField> = (0..4)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
+        .collect();
+    let layer: Vec<MyFieldExt> = domain
+        .iter()
+        .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+        .collect();
+    let inverse_twiddles = fri_inverse_twiddles(&domain);
+    assert_eq!(inverse_twiddles.len(), 2);
+    assert_eq!(inverse_twiddles[1] * domain[1], MyField::ONE);
+    let next_layer = compute_next_fri_layer(&layer, &inverse_twiddles, beta, 2);
+    let expected_next_layer = vec![MyFieldExt::from(586), -MyFieldExt::from(564)]; // 11 + 23*x at 25 and -25
     assert_eq!(next_layer, expected_next_layer, "next_layer does not match");
+    let next_layer = compute_next_fri_layer(&layer, &inverse_twiddles, beta, 4);
+    assert_eq!(next_layer, vec![MyFieldExt::from(586)]);
+}
+
+#[test]
+fn test_compute_next_fri_layer_matches_next_fri_polynomial() {
+    // Create a Group of size 256 and its coset
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 256]);
+    let domain: Vec<MyField> = (0..256)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
+        .collect();
+    let inverse_twiddles = fri_inverse_twiddles(&domain);
+    let rng = &mut test_rng();
+    for folding_factor in [2, 4, 8, 16] {
+        // Two layers are folded, the second one lying on the domain raised to the power folding_factor
+        let mut poly = DensePolynomial::<MyFieldExt>::rand(63, rng);
+        let mut fri_domain = domain.clone();
+        let mut layer: Vec<MyFieldExt> = domain
+            .iter()
+            .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+            .collect();
+        for _ in 0..2 {
+            let beta = MyFieldExt::rand(rng);
+            poly = compute_next_fri_polynomial(&poly, beta, folding_factor);
+            fri_domain = compute_next_fri_domain(&fri_domain, folding_factor);
+            layer = compute_next_fri_layer(&layer, &inverse_twiddles, beta, folding_factor);
+            let expected_layer: Vec<MyFieldExt> = fri_domain
+                .iter()
+                .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+                .collect();
+            assert_eq!(layer, expected_layer);
+        }
+    }
 }
 
 #[test]
 fn test_generate_fri_commitments() {
     let poly = DensePolynomial {
         coeffs: vec![
-            MyField::ONE,
-            MyField::from(2),
-            MyField::from(3),
-            MyField::from(4),
+            MyFieldExt::ONE,
+            MyFieldExt::from(2),
+            MyFieldExt::from(3),
+            MyFieldExt::from(4),
         ],
     }; // 1 + 2*x + 3*x^2 + 4*x^3
-    let poly_domain = vec![
-        MyField::ONE,
-        MyField::from(2),
-        MyField::from(3),
-        MyField::from(4),
-    ];
-    let poly_eval: Vec<MyField> = poly_domain
+       // Create a coset of a Group of size 4, the folding pairing x and -x
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 4]);
+    let poly_domain: Vec<MyField> = (0..4)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
+        .collect();
+    let poly_eval: Vec<MyFieldExt> = poly_domain
         .iter()
-        .map(|point| poly.evaluate(&point))
+        .map(|point| poly.evaluate(&MyFieldExt::from_base_prime_field(*point)))
         .collect();
-    let poly_merkle = create_merkle_tree(&poly_eval);
+    let poly_merkle = create_fri_merkle_tree::<Sha256>(&poly_eval, 2);
+    let poly_root = poly_merkle.root();
     let mut channel = Channel::new();
     // we first send some data otherwise if we receive directly random field element it will be equal to 0
-    let data = [01u8; 32];
+    let data = [1u8; 32];
     channel.send(&data.to_vec());
-    let (fri_polys, fri_domains, fri_layers, fri_merkles) =
-        generate_fri_commitments(&poly, &poly_domain, &poly_eval, &poly_merkle, &mut channel);
-    // test fri_polys
-    assert_eq!(fri_polys.len(), 3);
-    assert_eq!(fri_polys[0], poly);
-    assert_eq!(
-        fri_polys[1],
-        DensePolynomial {
-            coeffs: vec![MyField::from(807540146), MyField::from(1615080293)]
-        }
-    );
-    assert_eq!(
-        fri_polys[2],
-        DensePolynomial {
-            coeffs: vec![MyField::from(1461010691)]
-        }
+    let mut expected_channel = channel.clone();
+    let (fri_layers, fri_merkles, fri_remainder) = generate_fri_commitments(
+        &poly_domain,
+        &poly_eval,
+        poly_merkle,
+        &[2, 2],
+        0,
+        0,
+        &mut channel,
     );
-    // test fri_domains
-    assert_eq!(fri_domains.len(), 3);
-    assert_eq!(fri_domains[0], poly_domain);
-    assert_eq!(fri_domains[1], vec![MyField::from(1), MyField::from(4)]);
-    assert_eq!(fri_domains[2], vec![MyField::from(1)]);
-    // test fri_layers
+    // test fri_layers, the betas being drawn from the extension field
+    // The first folding gives (1 + 2 beta) + (3 + 4 beta) x, evaluated at 25 and -25
     assert_eq!(fri_layers.len(), 3);
     assert_eq!(fri_layers[0], poly_eval);
     assert_eq!(
         fri_layers[1],
-        vec![MyField::from(2422620439_u32), MyField::from(825410372)]
+        vec![
+            ext(722873027, 765032011, 2630984903),
+            ext(2779345542, 1412452893, 2904061381)
+        ]
     );
-    assert_eq!(fri_layers[2], vec![MyField::from(1461010691)]);
-    // test fri_merkles
-    assert_eq!(fri_merkles.len(), 3);
-    assert_eq!(fri_merkles[0].root(), poly_merkle.root());
+    assert_eq!(fri_layers[2], vec![ext(1078721194, 2629953673, 2640552697)]);
+    // test fri_merkles, the last layer is not committed
+    assert_eq!(fri_merkles.len(), 2);
+    assert_eq!(fri_merkles[0].root(), poly_root);
     assert_eq!(
         fri_merkles[1].root(),
-        create_merkle_tree(&vec![
-            MyField::from(2422620439_u32),
-            MyField::from(825410372)
-        ])
-        .root()
+        create_fri_merkle_tree::<Sha256>(&fri_layers[1], 2).root()
     );
+    // test fri_remainder, sent in place of the last layer
+    assert_eq!(fri_remainder, vec![ext(1078721194, 2629953673, 2640552697)]);
+    // A beta is drawn before each folding, the commitment to the folded layer being sent in between
+    let _ = expected_channel.receive_random_extension_field_element();
+    expected_channel.send(&fri_merkles[1].root().to_vec());
+    let _ = expected_channel.receive_random_extension_field_element();
+    expected_channel.send(&extension_field_elements_to_bytes(&fri_remainder));
+    assert_eq!(channel, expected_channel);
+}
+
+#[test]
+fn test_create_fri_merkle_tree() {
+    let layer: Vec<MyFieldExt> = (0..8).map(|i| ext(i, 10 + i, 20 + i)).collect();
+    let merkle = create_fri_merkle_tree::<Sha256>(&layer, 2);
+    // The i-th leaf holds the coefficients of the elements at i and i + 4
+    assert_eq!(merkle.leaves_len(), 4);
+    let columns: Vec<Vec<MyField>> = [0, 10, 20, 4, 14, 24]
+        .iter()
+        .map(|&start| (start..start + 4).map(MyField::from).collect())
+        .collect();
     assert_eq!(
-        fri_merkles[2].root(),
-        create_merkle_tree(&vec![MyField::from(1461010691)]).root()
+        merkle.root(),
+        create_merkle_tree_from_columns::<Sha256>(&columns).root()
     );
-    // test channel's proof
-    assert_eq!(channel.proof.len(), 6);
+    assert!(verify_multi_decommitment::<Sha256>(
+        4,
+        &[2],
+        &[flatten_extension_field_elements(&[layer[2], layer[6]])],
+        &get_multi_proof(&merkle, &[2], 0),
+        &[merkle.root()]
+    ));
+    // Folding by 4, the i-th leaf holds the elements at i, i + 2, i + 4 and i + 6
+    let merkle = create_fri_merkle_tree::<Sha256>(&layer, 4);
+    assert_eq!(merkle.leaves_len(), 2);
+    assert!(verify_multi_decommitment::<Sha256>(
+        2,
+        &[1],
+        &[flatten_extension_field_elements(&[
+            layer[1], layer[3], layer[5], layer[7]
+        ])],
+        &get_multi_proof(&merkle, &[1], 0),
+        &[merkle.root()]
+    ));
+}
+
+#[test]
+fn test_fold_coset() {
+    // Create a Group of size 64 and its coset
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 64]);
+    let domain: Vec<MyField> = (0..64)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
+        .collect();
+    let rng = &mut test_rng();
+    let poly = DensePolynomial::<MyFieldExt>::rand(63, rng);
+    let layer: Vec<MyFieldExt> = domain
+        .iter()
+        .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+        .collect();
+    let beta = MyFieldExt::rand(rng);
+    for folding_factor in [2, 4, 8, 16] {
+        let next_domain = compute_next_fri_domain(&domain, folding_factor);
+        let next_layer =
+            compute_next_fri_layer(&layer, &fri_inverse_twiddles(&domain), beta, folding_factor);
+        // The coset of the i-th element folds into the i-th element of the next layer
+        let num_leaves = next_domain.len();
+        for i in [0, 3, num_leaves - 1] {
+            let coset: Vec<MyField> = (0..folding_factor)
+                .map(|j| domain[i + j * num_leaves])
+                .collect();
+            let values: Vec<MyFieldExt> = (0..folding_factor)
+                .map(|j| layer[i + j * num_leaves])
+                .collect();
+            assert_eq!(fold_coset(&coset, &values, beta), next_layer[i]);
+            assert_eq!(next_domain[i], coset[1].pow([folding_factor as u64]));
+        }
+    }
 }
 
 #[test]
 fn test_decommit_on_fri_layers() {
     let poly = DensePolynomial {
         coeffs: vec![
-            MyField::ONE,
-            MyField::from(2),
-            MyField::from(3),
-            MyField::from(4),
+            MyFieldExt::ONE,
+            MyFieldExt::from(2),
+            MyFieldExt::from(3),
+            MyFieldExt::from(4),
         ],
     }; // 1 + 2*x + 3*x^2 + 4*x^3
-    let poly_domain = vec![
-        MyField::ONE,
-        MyField::from(2),
-        MyField::from(3),
-        MyField::from(4),
-    ];
-    let poly_eval: Vec<MyField> = poly_domain
+       // Create a coset of a Group of size 4, the folding pairing x and -x
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 4]);
+    let poly_domain: Vec<MyField> = (0..4)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
+        .collect();
+    let poly_eval: Vec<MyFieldExt> = poly_domain
         .iter()
-        .map(|point| poly.evaluate(&point))
+        .map(|point| poly.evaluate(&MyFieldExt::from_base_prime_field(*point)))
         .collect();
-    let poly_merkle = create_merkle_tree(&poly_eval);
+    let poly_merkle = create_fri_merkle_tree::<Sha256>(&poly_eval, 2);
     let mut channel = Channel::new();
     // we first send some data otherwise if we receive directly random field element it will be equal to 0
-    let data = [01u8; 32];
+    let data = [1u8; 32];
     channel.send(&data.to_vec());
     // we generate fri commitments
-    let (_, _, fri_layers, fri_merkles) =
-        generate_fri_commitments(&poly, &poly_domain, &poly_eval, &poly_merkle, &mut channel);
-    decommit_on_fri_layers(1, &fri_layers, &fri_merkles, &mut channel);
-    assert_eq!(channel.proof.len(), 15, "proof must contain 15 elements"); // 9 sendings added by decommit_on_fri_layers
-    assert_eq!(
-        channel.proof[6],
-        Member::new(Type::Send, (49_u64).to_le_bytes().to_vec())
-    ); // decommit of id of 1st merkle
-    assert_eq!(
-        channel.proof[7],
-        Member::new(
-            Type::Send,
-            decode("a111f275cc2e7588000001d300a31e76336d15b9d314cd1a1d8f3d3556975eed") // H(0a00000000000000)
-                .unwrap()
-                .iter()
-                .chain(
-                    decode("84a9bfa2c0c8ab01fd85ecfa9f7bb7da20822b54ca1cb5c03d5f9f122491ffe2") // H(H(8e00000000000000), H(3901000000000000))
-                        .unwrap()
-                        .iter()
-                )
-                .copied()
-                .collect()
-        )
-    ); // decommit of authentication path of id of 1st merkle
-    assert_eq!(
-        channel.proof[8],
-        Member::new(Type::Send, (313_u64).to_le_bytes().to_vec())
-    ); // decommit of sibling id of 1st merkle
-    assert_eq!(
-        channel.proof[9],
-        Member::new(
-            Type::Send,
-            decode("0c8b964f5ed84d78c6473e73f5d9307e453cd813c44a012f1b39c1c0262a56d3") // H(8e00000000000000)
-                .unwrap()
-                .iter()
-                .chain(
-                    decode("a3de3569dba30b2dbfd4aeffd9b95912536ac0b7d2d55cb6033c6e776ac10dd6") // H(H(0a00000000000000), H(3100000000000000))
-                        .unwrap()
-                        .iter()
-                )
-                .copied()
-                .collect()
-        )
-    ); // decommit of authentication path of sibling id of 1st merkle
-    assert_eq!(
-        channel.proof[10],
-        Member::new(Type::Send, (825410372_u64).to_le_bytes().to_vec())
-    ); // decommit of id of 2nd merkle
+    let (fri_layers, fri_merkles, _) = generate_fri_commitments(
+        &poly_domain,
+        &poly_eval,
+        poly_merkle,
+        &[2, 2],
+        0,
+        0,
+        &mut channel,
+    );
+    let mut expected_channel = channel.clone();
+    let openings = decommit_on_fri_layers(1, &fri_layers, &mut channel);
     assert_eq!(
-        channel.proof[11],
-        Member::new(
-            Type::Send,
-            decode("4f304a3a93ff3cceb6181215f9da922481a7473d8508f9cbaeddb5c318fc7039") // H(1741669000000000)
-                .unwrap()
-        )
-    ); // decommit of authentication path of id of 2nd merkle
+        openings[0].values,
+        vec![ext(2456173531, 0, 0), ext(765051794, 0, 0)]
+    ); // decommit of the coset of id 1 in the 1st layer, the elements at ids 1 and 3
     assert_eq!(
-        channel.proof[12],
-        Member::new(Type::Send, (2422620439_u64).to_le_bytes().to_vec())
-    ); // decommit of sibling id of 2nd merkle
+        openings[1].values,
+        vec![
+            ext(722873027, 765032011, 2630984903),
+            ext(2779345542, 1412452893, 2904061381)
+        ]
+    ); // decommit of the coset of id 1 in the 2nd layer, the elements at ids 0 and 1
+    for opening in &openings {
+        expected_channel.send(&extension_field_elements_to_bytes(&opening.values));
+    }
+    assert_eq!(channel, expected_channel);
+    assert_eq!(fri_opened_value(1, 2, 2, &openings[1]), fri_layers[1][1]);
+    assert_eq!(openings.len(), 2, "the last layer is not opened");
+
+    // Multi-proofs of the query, the evaluations of poly standing for a trace of a single column
+    let trace_merkle = create_merkle_tree::<Sha256>(&[10, 49, 142, 313].map(MyField::from));
+    let (trace_proof, fri_proofs) =
+        decommit_merkle_proofs(&[1], 1, 1, &trace_merkle, &fri_merkles, 0, &mut channel);
     assert_eq!(
-        channel.proof[13],
-        Member::new(
-            Type::Send,
-            decode("2ffef4ff8b90f8a3f2beae33f39b3f356b6f0587593c9d12a77de88fcac62583") // H(44c3323100000000)
-                .unwrap()
-        )
-    ); // decommit of authentication path of sibling id of 2nd merkle
+        trace_proof.to_bytes(),
+        [
+            decode("82451e648f5a389de8890552888439415478ffa3d3595a17639e69f2b839486a") // H(00 0a00000000000000)
+                .unwrap(),
+            decode("781dab438917474958266153f96e336de73688d54590b22c5e0d96db02b87203") // H(01 H(00 8e00000000000000) H(00 3901000000000000))
+                .unwrap(),
+        ]
+        .concat()
+    ); // authentication path of the element at id 1
+    assert_eq!(fri_proofs.len(), 2);
     assert_eq!(
-        channel.proof[14],
-        Member::new(Type::Send, (1461010691_u64).to_le_bytes().to_vec())
-    ); // decommit of last element (constant polynomial)
+        fri_proofs[0].to_bytes(),
+        decode("2b835683197f01b0f2423e723839d4e7b44692f6e1530d6537875f2e61b2d30e") // H(00 4a02000000000000 0000000000000000 0000000000000000 4ffeffbf00000000 0000000000000000 0000000000000000)
+            .unwrap()
+    ); // the leaf at id 1 holds the elements at ids 1 and 3, its sibling the ones at ids 0 and 2
+    assert!(
+        fri_proofs[1].0.is_empty(),
+        "the 2nd layer has a single leaf"
+    );
+    // The multi-proofs are sent in order
+    expected_channel.send(&trace_proof.to_bytes());
+    for fri_proof in &fri_proofs {
+        expected_channel.send(&fri_proof.to_bytes());
+    }
+    assert_eq!(channel, expected_channel);
 }
 
 #[test]
@@ -265,13 +379,13 @@ fn test_decommit_on_query() {
         ],
     }; // 1 + 2*x + 3*x^2 + 4*x^3
        // Create a Group of size 4
-    let g = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 4]));
+    let g = MyField::GENERATOR.pow([3221225472_u64 / 4]);
     let mut G = vec![MyField::ONE];
     for i in 1..4 {
         G.push(G[i - 1] * g);
     }
     // Create a Group of size 32
-    let h = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 32]));
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 32]);
     let mut H = vec![MyField::ONE];
     for i in 1..32 {
         H.push(H[i - 1] * h);
@@ -280,75 +394,181 @@ fn test_decommit_on_query() {
     let w = MyField::GENERATOR;
     let eval_domain: Vec<MyField> = H.iter().map(|x| w * x).collect();
     // Evaluate on the Coset
-    let f_eval: Vec<MyField> = eval_domain.iter().map(|point| f.evaluate(&point)).collect();
+    let f_eval: Vec<MyField> = eval_domain.iter().map(|point| f.evaluate(point)).collect();
     // Commit f_eval on merkle tree
-    let f_merkle = create_merkle_tree(&f_eval);
+    let f_merkle = create_merkle_tree::<Sha256>(&f_eval);
     // Send root of f_merkle
     let mut channel = Channel::new();
-    let root = f_merkle.root().unwrap();
+    let root = f_merkle.root();
     channel.send(&root.to_vec());
     // we generate fri commitments
-    let (_, _, fri_layers, fri_merkles) =
-        generate_fri_commitments(&f, &eval_domain, &f_eval, &f_merkle, &mut channel);
-    // set query index
-    let id = 2;
-    decommit_on_query(
-        id,
-        &f_eval,
-        &f_merkle,
-        &fri_layers,
-        &fri_merkles,
+    // FRI runs in the extension field, the trace in the base field
+    let f_ext_eval: Vec<MyFieldExt> = f_eval
+        .iter()
+        .map(|value| MyFieldExt::from_base_prime_field(*value))
+        .collect();
+    let (fri_layers, fri_merkles, _) = generate_fri_commitments(
+        &eval_domain,
+        &f_ext_eval,
+        create_fri_merkle_tree(&f_ext_eval, 2),
+        &[2, 2],
+        0,
+        0,
         &mut channel,
     );
-    // test length of proof
-    assert_eq!(channel.proof.len(), 21, "proof must contain 21 elements");
-    // test f(x) and its authentication path
-    let f_x = f.evaluate(&(w * h.pow(&(vec![2]))));
+    // set query index
+    let id = 2;
+    let mut expected_channel = channel.clone();
+    let query = decommit_on_query(id, 8, 3, &[f_eval], &fri_layers, &mut channel);
+    // test the sendings of f(x), f(gx) and f(g^2x)
+    let f_x = f.evaluate(&(w * h.pow([2])));
+    let f_gx = f.evaluate(&(w * h.pow([2 + 8])));
+    let f_g2x = f.evaluate(&(w * h.pow([2 + 16])));
+    for value in [f_x, f_gx, f_g2x] {
+        expected_channel.send(&value.into_bigint().to_bytes_le());
+    }
+    // The last 2 sendings relate to decommitment on FRI layers and were tested in test_decommit_on_fri_layers
+    for opening in &query.fri_layers {
+        expected_channel.send(&extension_field_elements_to_bytes(&opening.values));
+    }
+    assert_eq!(channel, expected_channel, "test of the sendings failed");
+    // test the returned opening
+    assert_eq!(query.id, 2);
+    assert_eq!(query.trace_frame.len(), 3);
+    assert_eq!(query.trace_frame[1].row, vec![f_gx]);
+    assert_eq!(query.fri_layers.len(), 2);
     assert_eq!(
-        channel.proof[6],
-        Member::new(Type::Send, f_x.into_bigint().to_bytes_le()),
-        "test of f(x) failed"
+        fri_opened_value(id, 32, 2, &query.fri_layers[0]),
+        MyFieldExt::from_base_prime_field(f_x)
     );
-    let authentication_path_f_x: Vec<[u8; 32]> = channel.proof[7]
-        .data
-        .chunks_exact(32)
-        .map(|chunk| <[u8; 32]>::try_from(chunk).unwrap())
-        .collect();
+    // The multi-proof of the trace authenticates the three rows at once
+    let (trace_proof, _) =
+        decommit_merkle_proofs(&[id], 8, 3, &f_merkle, &fri_merkles, 0, &mut channel);
     assert!(
-        verify_decommitment(2, f_x, &authentication_path_f_x, root),
-        "verification of authentication path of f(x) failed"
+        verify_multi_decommitment::<Sha256>(
+            32,
+            &trace_frame_ids(id, 8, 3, 32),
+            &[vec![f_x], vec![f_gx], vec![f_g2x]],
+            &trace_proof.0,
+            &[root]
+        ),
+        "verification of multi-proof of the trace failed"
+    );
+}
+
+#[test]
+fn test_trace_frame_ids_wrap_around() {
+    assert_eq!(trace_frame_ids(5, 8, 3, 32), vec![5, 13, 21]);
+    // The rows at gx and g^2x of a query at the end of the eval domain are found at its beginning
+    assert_eq!(trace_frame_ids(30, 8, 3, 32), vec![30, 6, 14]);
+}
+
+#[test]
+fn test_batch_fri_evaluations() {
+    let evaluations = vec![
+        vec![MyFieldExt::from(1), MyFieldExt::from(2)],
+        vec![MyFieldExt::from(3), MyFieldExt::from(4)],
+    ];
+    let coefficients = [MyFieldExt::from(5), ext(0, 1, 0)];
+    assert_eq!(
+        batch_fri_evaluations(&evaluations, &coefficients),
+        vec![ext(5, 3, 0), ext(10, 4, 0)]
     );
-    // test f(gx) and its authentication path
-    let f_gx = f.evaluate(&(w * h.pow(&(vec![2 + 8]))));
+    let openings = [
+        FriLayerOpening {
+            values: evaluations[0].clone(),
+        },
+        FriLayerOpening {
+            values: evaluations[1].clone(),
+        },
+    ];
     assert_eq!(
-        channel.proof[8],
-        Member::new(Type::Send, f_gx.into_bigint().to_bytes_le()),
-        "test of f(gx) failed"
+        batch_fri_openings(&openings, &coefficients).values,
+        vec![ext(5, 3, 0), ext(10, 4, 0)]
     );
-    let authentication_path_f_gx: Vec<[u8; 32]> = channel.proof[9]
-        .data
-        .chunks_exact(32)
-        .map(|chunk| <[u8; 32]>::try_from(chunk).unwrap())
+}
+
+#[test]
+fn test_generate_batched_fri_commitments() {
+    // Create a Group of size 32 and its coset
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 32]);
+    let domain: Vec<MyField> = (0..32)
+        .map(|i| MyField::GENERATOR * h.pow([i as u64]))
         .collect();
-    assert!(
-        verify_decommitment(2 + 8, f_gx, &authentication_path_f_gx, root),
-        "verification of authentication path of f(gx) failed"
+    let rng = &mut test_rng();
+    let evaluations: Vec<Vec<MyFieldExt>> = (0..3)
+        .map(|_| {
+            let poly = DensePolynomial::<MyFieldExt>::rand(7, rng);
+            domain
+                .iter()
+                .map(|x| poly.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+                .collect()
+        })
+        .collect();
+    let merkles: Vec<_> = evaluations
+        .iter()
+        .map(|evaluation| create_fri_merkle_tree::<Sha256>(evaluation, 2))
+        .collect();
+    let committed: Vec<CommittedEvaluations<Sha256>> = zip(&evaluations, &merkles)
+        .map(|(evaluation, merkle)| (evaluation.as_slice(), merkle))
+        .collect();
+    let mut channel = Channel::new();
+    let (coefficients, (fri_layers, fri_merkles, fri_remainder)) =
+        generate_batched_fri_commitments(&domain, &committed, &[2, 2, 2], 0, 0, &mut channel);
+    // The first layer is the combination of the evaluations, it is not committed
+    assert_eq!(coefficients.len(), 3);
+    assert_eq!(
+        fri_layers[0],
+        batch_fri_evaluations(&evaluations, &coefficients)
     );
-    // test f(g^2x) and its authentication path
-    let f_g2x = f.evaluate(&(w * h.pow(&(vec![2 + 16]))));
+    assert_eq!(fri_layers.len(), 4);
+    assert_eq!(fri_merkles.len(), 2);
+    assert_eq!(fri_remainder.len(), 1);
+    // The coefficients are drawn first, then a beta before each folding, the commitments to the folded layers but the
+    // last one being sent in between, and finally the remainder
+    let mut expected_channel = Channel::new();
     assert_eq!(
-        channel.proof[10],
-        Member::new(Type::Send, f_g2x.into_bigint().to_bytes_le()),
-        "test of f(gx) failed"
+        expected_channel.receive_random_extension_field_elements(3),
+        coefficients
     );
-    let authentication_path_f_g2x: Vec<[u8; 32]> = channel.proof[11]
-        .data
-        .chunks_exact(32)
-        .map(|chunk| <[u8; 32]>::try_from(chunk).unwrap())
-        .collect();
-    assert!(
-        verify_decommitment(2 + 16, f_g2x, &authentication_path_f_g2x, root),
-        "verification of authentication path of f(gx) failed"
+    for layer in 0..3 {
+        let _ = expected_channel.receive_random_extension_field_element();
+        if let Some(merkle) = fri_merkles.get(layer) {
+            expected_channel.send(&merkle.root().to_vec());
+        }
+    }
+    expected_channel.send(&extension_field_elements_to_bytes(&fri_remainder));
+    assert_eq!(channel, expected_channel);
+
+    // Each function is opened on the coset of the query, then the folded layers but the last one
+    let query = decommit_on_batched_query(5, &committed, &fri_layers, &mut channel);
+    for opening in query.batched.iter().chain(&query.fri_layers) {
+        expected_channel.send(&extension_field_elements_to_bytes(&opening.values));
+    }
+    assert_eq!(channel, expected_channel);
+    assert_eq!(query.batched.len(), 3);
+    assert_eq!(
+        query.batched[1].values,
+        vec![evaluations[1][5], evaluations[1][21]]
     );
-    // The last 9 sendings relate to decommitment on FRI layers and were tested in test_decommit_on_fri_layers
+    assert_eq!(query.fri_layers.len(), 2);
+    assert_eq!(
+        fri_opened_value(5, 16, 2, &query.fri_layers[0]),
+        fri_layers[1][5]
+    );
+    let (batched_proofs, fri_proofs) =
+        decommit_batched_merkle_proofs(&[5], &committed, &fri_merkles, 0, &mut channel);
+    assert_eq!(batched_proofs.len(), 3);
+    assert_eq!(fri_proofs.len(), 2);
+    for merkle_proof in batched_proofs.iter().chain(&fri_proofs) {
+        expected_channel.send(&merkle_proof.to_bytes());
+    }
+    assert_eq!(channel, expected_channel);
+    assert!(verify_multi_decommitment::<Sha256>(
+        16,
+        &[5],
+        &[flatten_extension_field_elements(&query.batched[1].values)],
+        &batched_proofs[1].0,
+        &[merkles[1].root()]
+    ));
 }
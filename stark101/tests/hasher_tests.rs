@@ -0,0 +1,99 @@
+use ark_ff::PrimeField;
+use hex::encode;
+use stark101::common::{
+    finite_fields::{field_element_to_bytes, MyField},
+    hasher::{Blake3, Hasher, Keccak256, Poseidon, Sha256},
+    merkle::{LEAF_PREFIX, NODE_PREFIX},
+    utils::concat_slices,
+};
+
+#[test]
+fn test_hash_empty_input() {
+    assert_eq!(
+        encode(Sha256::hash(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        encode(Blake3::hash(b"")),
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    );
+    assert_eq!(
+        encode(Keccak256::hash(b"")),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+}
+
+#[test]
+fn test_merge_hashes_prefixed_concatenation() {
+    let left = Sha256::hash(b"left");
+    let right = Sha256::hash(b"right");
+    let node = [&[NODE_PREFIX], left.as_slice(), &right].concat();
+    assert_eq!(Sha256::merge(&left, &right), Sha256::hash(&node));
+    assert_eq!(Keccak256::merge(&left, &right), Keccak256::hash(&node));
+    assert_ne!(
+        Sha256::merge(&left, &right),
+        Sha256::hash(&concat_slices(&left, &right))
+    );
+}
+
+#[test]
+fn test_poseidon() {
+    let digest = Poseidon::hash(b"stark101");
+    assert_eq!(
+        digest,
+        Poseidon::hash(b"stark101"),
+        "hash must be deterministic"
+    );
+    // The digest is made of 8 canonical field elements of 4 bytes
+    for bytes in digest.chunks(4) {
+        let value = u32::from_le_bytes(bytes.try_into().unwrap());
+        assert!((value as u64) < MyField::MODULUS.0[0]);
+    }
+    // Padding must distinguish trailing zeros
+    let inputs: [&[u8]; 4] = [b"", b"\0", b"\0\0\0", &[0; 24]];
+    for (i, a) in inputs.iter().enumerate() {
+        for b in &inputs[i + 1..] {
+            assert_ne!(Poseidon::hash(a), Poseidon::hash(b));
+        }
+    }
+    let left = Poseidon::hash(b"left");
+    let right = Poseidon::hash(b"right");
+    assert_ne!(
+        Poseidon::merge(&left, &right),
+        Poseidon::merge(&right, &left)
+    );
+}
+
+#[test]
+fn test_hash_leaf() {
+    let row = [MyField::from(1), MyField::from(3221225472_u64)];
+    let bytes: Vec<u8> = std::iter::once(LEAF_PREFIX)
+        .chain(row.iter().flat_map(field_element_to_bytes))
+        .collect();
+    assert_eq!(Sha256::hash_leaf(&row), Sha256::hash(&bytes));
+    assert_eq!(Blake3::hash_leaf(&row), Blake3::hash(&bytes));
+    // Poseidon absorbs the field elements themselves, the padding distinguishing trailing zeros
+    assert_ne!(Poseidon::hash_leaf(&row), Poseidon::hash(&bytes));
+    let rows: [&[MyField]; 4] = [
+        &[],
+        &[MyField::from(0)],
+        &[MyField::from(0); 15],
+        &[MyField::from(0); 16],
+    ];
+    for (i, a) in rows.iter().enumerate() {
+        for b in &rows[i + 1..] {
+            assert_ne!(Poseidon::hash_leaf(a), Poseidon::hash_leaf(b));
+        }
+    }
+    // A leaf of 16 elements is not a node made of the same elements
+    let left = Poseidon::hash_leaf(&row);
+    let right = Poseidon::hash_leaf(&row[..1]);
+    let children: Vec<MyField> = concat_slices(&left, &right)
+        .chunks(4)
+        .map(MyField::from_le_bytes_mod_order)
+        .collect();
+    assert_ne!(
+        Poseidon::hash_leaf(&children),
+        Poseidon::merge(&left, &right)
+    );
+}
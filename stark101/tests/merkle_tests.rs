@@ -1,18 +1,22 @@
-use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
 use ark_std::{rand::Rng, test_rng};
 use hex::encode;
-use stark101::common::{finite_fields::MyField, merkle::*};
+use stark101::common::{
+    finite_fields::MyField,
+    hasher::{Hasher, Poseidon, Sha256},
+    merkle::*,
+};
 
 #[test]
 fn test_create_tree_with_2_leaves() {
     let data = vec![MyField::from(1), MyField::from(2)];
-    let merkle_tree = create_merkle_tree(&data);
-    // H0 = H(0100000000000000) = 7c9fa136d4413fa6173637e883b6998d32e1d675f88cddff9dcbcf331820f4b8
-    // H1 = H(0200000000000000) = d86e8112f3c4c4442126f8e9f44f16867da487f29052bf91b810457db34209a4
-    let root = merkle_tree.root().unwrap();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    // H0 = H(00 0100000000000000) = 51b09ceccfbec44595dd4241e6e2a693d279b72c899c8f60ec63524fe58b1d4f
+    // H1 = H(00 0200000000000000) = 3b7aee3e7f3e7913273d9e7860e0388497282ed34eac943c8f923d073d774cb3
+    let root = merkle_tree.root();
     assert_eq!(
         encode(root),
-        "c06b7afada32b8e5e3e62b0a563e632f68dcef97d8dd39de5c1b3fe4132aaea1",
+        "ec76eae5cb6aed10bacf73cdf2eec6163eb6f06cd74db2de7fee5c9e5382fce1",
         "root is not matching!"
     );
 }
@@ -25,17 +29,17 @@ fn test_create_tree_with_4_leaves() {
         MyField::from(3),
         MyField::from(4),
     ];
-    let merkle_tree = create_merkle_tree(&data);
-    // H00 = H(0100000000000000) = 7c9fa136d4413fa6173637e883b6998d32e1d675f88cddff9dcbcf331820f4b8
-    // H01 = H(0200000000000000) = d86e8112f3c4c4442126f8e9f44f16867da487f29052bf91b810457db34209a4
-    // H10 = H(0300000000000000) = 35be322d094f9d154a8aba4733b8497f180353bd7ae7b0a15f90b586b549f28b
-    // H11 = H(0400000000000000) = f0a0278e4372459cca6159cd5e71cfee638302a7b9ca9b05c34181ac0a65ac5d
-    // H0 = c06b7afada32b8e5e3e62b0a563e632f68dcef97d8dd39de5c1b3fe4132aaea1
-    // H1 = 3b95ab12601f8fa42464588a735ac0ffda59c8c49e79712770229adf2b9e6ada
-    let root = merkle_tree.root().unwrap();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    // H00 = H(00 0100000000000000) = 51b09ceccfbec44595dd4241e6e2a693d279b72c899c8f60ec63524fe58b1d4f
+    // H01 = H(00 0200000000000000) = 3b7aee3e7f3e7913273d9e7860e0388497282ed34eac943c8f923d073d774cb3
+    // H10 = H(00 0300000000000000) = 5808c92598bb39efe08f1f64cba66bcce9d2e5e97ff3c2319e47b5c94817ad22
+    // H11 = H(00 0400000000000000) = 47b5bab475da2f3f91ed6fd44b895fe8e74024319ed2b9bc0fb0c46b3a605a75
+    // H0 = H(01 H00 H01) = ec76eae5cb6aed10bacf73cdf2eec6163eb6f06cd74db2de7fee5c9e5382fce1
+    // H1 = H(01 H10 H11) = 9605926f0edd326485cf142d18edb1126a08f333d5078ce97de55ad932c1afaf
+    let root = merkle_tree.root();
     assert_eq!(
         encode(root),
-        "8a977dd50bf34d05d66ca85bcc0c2684482c9c3284720c3d1037af248f3c572f",
+        "082e8e29b028ef12e81530323943dc08834f103e41a73e41c4cbd14b115f85c9",
         "root is not matching!"
     );
 }
@@ -48,7 +52,7 @@ fn test_get_authentication_path() {
         MyField::from(3),
         MyField::from(4),
     ];
-    let merkle_tree = create_merkle_tree(&data);
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
     let authentication_path = get_authentication_path(&merkle_tree, 1); // authentication-path of 2nd element
     assert_eq!(
         authentication_path.len(),
@@ -57,12 +61,12 @@ fn test_get_authentication_path() {
     );
     assert_eq!(
         encode(authentication_path[0]),
-        "7c9fa136d4413fa6173637e883b6998d32e1d675f88cddff9dcbcf331820f4b8",
+        "51b09ceccfbec44595dd4241e6e2a693d279b72c899c8f60ec63524fe58b1d4f",
         "1st hash of authentication path is not matching!"
     );
     assert_eq!(
         encode(authentication_path[1]),
-        "3b95ab12601f8fa42464588a735ac0ffda59c8c49e79712770229adf2b9e6ada",
+        "9605926f0edd326485cf142d18edb1126a08f333d5078ce97de55ad932c1afaf",
         "2nd hash of authentication path is not matching!"
     );
 }
@@ -75,16 +79,16 @@ fn test_verify_decommitment() {
         MyField::from(3),
         MyField::from(4),
     ];
-    let merkle_tree = create_merkle_tree(&data);
-    let root = merkle_tree.root().unwrap();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    let root = merkle_tree.root();
     let authentication_path2 = get_authentication_path(&merkle_tree, 1); // authentication-path of 2nd element
     assert!(
-        verify_decommitment(1, MyField::from(2), &authentication_path2, root),
+        verify_decommitment::<Sha256>(1, MyField::from(2), &authentication_path2, root),
         "verification of decommitment of 2nd element failed"
     );
     let authentication_path3 = get_authentication_path(&merkle_tree, 2); // authentication-path of 3rd element
     assert!(
-        verify_decommitment(2, MyField::from(3), &authentication_path3, root),
+        verify_decommitment::<Sha256>(2, MyField::from(3), &authentication_path3, root),
         "verification of decommitment of 3rd element failed"
     );
 }
@@ -95,14 +99,288 @@ fn test_verify_decommitment_random() {
     for i in 1..=15 {
         let data_length = 1 << i;
         let data: Vec<MyField> = (0..data_length).map(|_| MyField::rand(rng)).collect();
-        let merkle_tree = create_merkle_tree(&data);
-        let root = merkle_tree.root().unwrap();
+        let merkle_tree = create_merkle_tree::<Sha256>(&data);
+        let root = merkle_tree.root();
         let leaf_id = rng.gen_range(0..data_length);
         let authentication_path = get_authentication_path(&merkle_tree, leaf_id);
         assert!(
-            verify_decommitment(leaf_id, data[leaf_id], &authentication_path, root),
+            verify_decommitment::<Sha256>(leaf_id, data[leaf_id], &authentication_path, root),
             "verification of decommitment failed with length: {}",
             data_length
         );
     }
 }
+
+#[test]
+fn test_create_tree_from_columns() {
+    let columns = vec![
+        vec![MyField::from(1), MyField::from(2)],
+        vec![MyField::from(3), MyField::from(4)],
+    ];
+    let merkle_tree = create_merkle_tree_from_columns::<Sha256>(&columns);
+    let single_column_tree = create_merkle_tree::<Sha256>(&columns[0]);
+    assert_ne!(
+        merkle_tree.root(),
+        single_column_tree.root(),
+        "leaves must hash all the columns"
+    );
+    // A single column gives the same tree as create_merkle_tree
+    assert_eq!(
+        create_merkle_tree_from_columns::<Sha256>(&columns[..1]).root(),
+        single_column_tree.root(),
+        "root is not matching!"
+    );
+}
+
+#[test]
+fn test_verify_row_decommitment() {
+    let rng = &mut test_rng();
+    let columns: Vec<Vec<MyField>> = (0..3)
+        .map(|_| (0..64).map(|_| MyField::rand(rng)).collect())
+        .collect();
+    let merkle_tree = create_merkle_tree_from_columns::<Sha256>(&columns);
+    let root = merkle_tree.root();
+    let leaf_id = rng.gen_range(0..64);
+    let row: Vec<MyField> = columns.iter().map(|column| column[leaf_id]).collect();
+    let authentication_path = get_authentication_path(&merkle_tree, leaf_id);
+    assert!(
+        verify_row_decommitment::<Sha256>(leaf_id, &row, &authentication_path, root),
+        "verification of row decommitment failed"
+    );
+    assert!(
+        !verify_row_decommitment::<Sha256>(leaf_id, &row[..2], &authentication_path, root),
+        "verification of partial row decommitment must fail"
+    );
+}
+
+#[test]
+fn test_verify_decommitment_poseidon() {
+    let data: Vec<MyField> = (0..16).map(MyField::from).collect();
+    let merkle_tree = create_merkle_tree::<Poseidon>(&data);
+    let root = merkle_tree.root();
+    assert_ne!(root, create_merkle_tree::<Sha256>(&data).root());
+    let authentication_path = get_authentication_path(&merkle_tree, 5);
+    assert!(verify_decommitment::<Poseidon>(
+        5,
+        data[5],
+        &authentication_path,
+        root
+    ));
+    assert!(!verify_decommitment::<Sha256>(
+        5,
+        data[5],
+        &authentication_path,
+        root
+    ));
+}
+
+#[test]
+fn test_leaf_and_node_hashes_are_separated() {
+    // A row whose bytes are the concatenation of two digests of canonical field elements
+    let row: Vec<MyField> = (1..=8).map(MyField::from).collect();
+    let bytes: Vec<u8> = row
+        .iter()
+        .flat_map(|x| x.into_bigint().to_bytes_le())
+        .collect();
+    let (left, right): ([u8; 32], [u8; 32]) = (
+        bytes[..32].try_into().unwrap(),
+        bytes[32..].try_into().unwrap(),
+    );
+    assert_ne!(hash_leaf::<Sha256>(&row), Sha256::merge(&left, &right));
+    assert_ne!(hash_leaf::<Poseidon>(&row), Poseidon::merge(&left, &right));
+    assert_eq!(
+        hash_leaf::<Sha256>(&row),
+        Sha256::hash(&[&[LEAF_PREFIX], bytes.as_slice()].concat())
+    );
+}
+
+#[test]
+fn test_reject_node_opened_as_leaf() {
+    let data: Vec<MyField> = (0..8).map(MyField::from).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    let root = merkle_tree.root();
+    let authentication_path = get_authentication_path(&merkle_tree, 0);
+    // The children of the first node above the leaves, passed off as the content of a leaf at that level
+    let children = [hash_leaf::<Sha256>(&data[..1]), authentication_path[0]].concat();
+    let mut hash = Sha256::hash(&[&[LEAF_PREFIX], children.as_slice()].concat());
+    assert_ne!(
+        hash,
+        Sha256::merge(&children[..32].try_into().unwrap(), &authentication_path[0])
+    );
+    for sibling in &authentication_path[1..] {
+        hash = Sha256::merge(&hash, sibling);
+    }
+    assert_ne!(hash, root, "a node must not be accepted as a leaf");
+}
+
+#[test]
+fn test_verify_multi_decommitment() {
+    let rng = &mut test_rng();
+    let data: Vec<MyField> = (0..64).map(|_| MyField::rand(rng)).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    let root = [merkle_tree.root()];
+    // Duplicated and unsorted ids, as opened by several queries
+    let leaf_ids = vec![40, 3, 41, 2, 40, 17];
+    let rows: Vec<Vec<MyField>> = leaf_ids.iter().map(|&id| vec![data[id]]).collect();
+    let nodes = get_multi_proof(&merkle_tree, &leaf_ids, 0);
+    let single_paths_len: usize = [2, 3, 17, 40, 41]
+        .iter()
+        .map(|&id| get_authentication_path(&merkle_tree, id).len())
+        .sum();
+    assert!(
+        nodes.len() < single_paths_len,
+        "multi-proof must share the common nodes"
+    );
+    assert!(verify_multi_decommitment::<Sha256>(
+        64, &leaf_ids, &rows, &nodes, &root
+    ));
+
+    let mut forged_rows = rows.clone();
+    forged_rows[5][0] += MyField::from(1);
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &forged_rows,
+        &nodes,
+        &root
+    ));
+    // A leaf opened twice with different rows
+    let mut forged_rows = rows.clone();
+    forged_rows[4][0] += MyField::from(1);
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &forged_rows,
+        &nodes,
+        &root
+    ));
+    // Missing or extra nodes
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &nodes[1..],
+        &root
+    ));
+    let extra_nodes = [nodes.as_slice(), &[[0u8; 32]]].concat();
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &extra_nodes,
+        &root
+    ));
+    // Leaf outside of the tree
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &[64],
+        &[vec![data[0]]],
+        &get_multi_proof(&merkle_tree, &[0], 0),
+        &root
+    ));
+}
+
+#[test]
+fn test_multi_proof_of_single_leaf_is_authentication_path() {
+    let data: Vec<MyField> = (0..16).map(MyField::from).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    assert_eq!(
+        get_multi_proof(&merkle_tree, &[11], 0),
+        get_authentication_path(&merkle_tree, 11)
+    );
+}
+
+#[test]
+fn test_merkle_cap() {
+    let data: Vec<MyField> = (0..16).map(MyField::from).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    assert_eq!(get_merkle_cap(&merkle_tree, 0), vec![merkle_tree.root()]);
+    // The cap of height 2 holds the roots of the subtrees of 4 leaves
+    let cap = get_merkle_cap(&merkle_tree, 2);
+    assert_eq!(cap.len(), 4);
+    for (i, node) in cap.iter().enumerate() {
+        let subtree = create_merkle_tree::<Sha256>(&data[4 * i..4 * (i + 1)]);
+        assert_eq!(*node, subtree.root());
+    }
+    // A cap higher than the tree holds its leaves
+    assert_eq!(merkle_cap_len(16, 6), 16);
+    assert_eq!(get_merkle_cap(&merkle_tree, 6), merkle_tree.leaves());
+}
+
+#[test]
+fn test_verify_multi_decommitment_against_cap() {
+    let rng = &mut test_rng();
+    let data: Vec<MyField> = (0..64).map(|_| MyField::rand(rng)).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    let leaf_ids = vec![5, 40, 41];
+    let rows: Vec<Vec<MyField>> = leaf_ids.iter().map(|&id| vec![data[id]]).collect();
+    let cap = get_merkle_cap(&merkle_tree, 3);
+    let nodes = get_multi_proof(&merkle_tree, &leaf_ids, 3);
+    // The nodes above the cap are dropped from the proof of the root
+    let full_nodes = get_multi_proof(&merkle_tree, &leaf_ids, 0);
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(nodes[..], full_nodes[..5]);
+    assert!(verify_multi_decommitment::<Sha256>(
+        64, &leaf_ids, &rows, &nodes, &cap
+    ));
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &full_nodes,
+        &cap
+    ));
+    // Each opening is checked against the cap entry of its subtree
+    let mut forged_cap = cap.clone();
+    forged_cap[5] = [0u8; 32];
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &nodes,
+        &forged_cap
+    ));
+    let mut swapped_cap = cap.clone();
+    swapped_cap.swap(0, 5);
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &nodes,
+        &swapped_cap
+    ));
+    assert!(!verify_multi_decommitment::<Sha256>(
+        64,
+        &leaf_ids,
+        &rows,
+        &nodes,
+        &cap[..7]
+    ));
+}
+
+#[test]
+fn test_tree_layers() {
+    let data: Vec<MyField> = (0..32).map(MyField::from).collect();
+    let merkle_tree = create_merkle_tree::<Sha256>(&data);
+    assert_eq!(merkle_tree.leaves_len(), 32);
+    assert_eq!(merkle_tree.layer(0), [merkle_tree.root()]);
+    assert_eq!(merkle_tree.layer(5), merkle_tree.leaves());
+    assert_eq!(merkle_tree.leaves()[7], hash_leaf::<Sha256>(&data[7..8]));
+    // Each node is the hash of its two children in the layer below
+    for depth in 0..5 {
+        let children = merkle_tree.layer(depth + 1);
+        for (i, node) in merkle_tree.layer(depth).iter().enumerate() {
+            assert_eq!(*node, Sha256::merge(&children[2 * i], &children[2 * i + 1]));
+        }
+    }
+    assert_eq!(
+        MerkleTree::<Sha256>::from_leaves(merkle_tree.leaves()).root(),
+        merkle_tree.root()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Number of leaves must be a power of two")]
+fn test_reject_tree_of_non_power_of_two_size() {
+    create_merkle_tree::<Sha256>(&[MyField::from(1), MyField::from(2), MyField::from(3)]);
+}
@@ -0,0 +1,127 @@
+use stark101::air::{fibonacci_sq::FibonacciSq, Air};
+use stark101::common::options::{HashFunction, ProofOptions};
+
+#[test]
+fn test_default_options() {
+    let options = ProofOptions::default();
+    assert_eq!(options.blowup, 8);
+    assert_eq!(options.num_queries, 3);
+    assert_eq!(options.folding_factor, 2);
+    assert_eq!(options.grinding_bits, 0);
+    assert_eq!(options.hash, HashFunction::Sha256);
+    assert_eq!(options.cap_height, 0);
+    assert_eq!(options.max_remainder_degree, 0);
+}
+
+#[test]
+fn test_parse_hash_function() {
+    assert_eq!("sha256".parse(), Ok(HashFunction::Sha256));
+    assert_eq!("blake3".parse(), Ok(HashFunction::Blake3));
+    assert_eq!("keccak256".parse(), Ok(HashFunction::Keccak256));
+    assert_eq!("poseidon".parse(), Ok(HashFunction::Poseidon));
+    assert!("md5".parse::<HashFunction>().is_err());
+}
+
+#[test]
+fn test_options_to_bytes() {
+    let options = ProofOptions::new(4, 5, 2, 0, HashFunction::Sha256, 0, 0);
+    let bytes = options.to_bytes();
+    assert_eq!(
+        bytes.len(),
+        5 * 8 + 4 + 1,
+        "length of serialized options is wrong"
+    );
+    assert_ne!(
+        bytes,
+        ProofOptions::default().to_bytes(),
+        "different options must be serialized differently"
+    );
+    assert_ne!(
+        bytes,
+        ProofOptions::new(4, 5, 2, 0, HashFunction::Keccak256, 0, 0).to_bytes(),
+        "options with different hash functions must be serialized differently"
+    );
+}
+
+#[test]
+fn test_derived_parameters() {
+    let air = FibonacciSq::default();
+    let options = ProofOptions::default();
+    assert_eq!(options.eval_domain_size(&air), 8192);
+    assert_eq!(options.num_fri_layers(&air), 10);
+    let options = ProofOptions::new(8, 3, 8, 0, HashFunction::Sha256, 0, 0);
+    assert_eq!(options.fri_folding_factors(&air), vec![8, 8, 8, 2]);
+    assert_eq!(options.num_fri_layers(&air), 4);
+    let options = ProofOptions::new(8, 3, 8, 0, HashFunction::Sha256, 0, 15);
+    assert_eq!(options.fri_folding_factors(&air), vec![8, 8]);
+    let options = ProofOptions::new(8, 3, 2, 0, HashFunction::Sha256, 0, 1023);
+    assert_eq!(options.num_fri_layers(&air), 0);
+    let options = ProofOptions::new(16, 3, 2, 0, HashFunction::Sha256, 0, 0);
+    assert_eq!(
+        options.eval_domain_size(&air),
+        16 * air.composition_degree_bound()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Folding factor must be 2, 4, 8 or 16")]
+fn test_invalid_folding_factor() {
+    ProofOptions::new(8, 3, 3, 0, HashFunction::Sha256, 0, 0);
+}
+
+#[test]
+#[should_panic(expected = "Grinding bits must be at most 32")]
+fn test_invalid_grinding_bits() {
+    ProofOptions::new(8, 3, 2, 33, HashFunction::Sha256, 0, 0);
+}
+
+#[test]
+#[should_panic(expected = "Blowup factor must be a power of two greater than 1")]
+fn test_invalid_blowup() {
+    ProofOptions::new(6, 3, 2, 0, HashFunction::Sha256, 0, 0);
+}
+
+#[test]
+#[should_panic(expected = "Maximal remainder degree must be a power of two minus one")]
+fn test_invalid_max_remainder_degree() {
+    ProofOptions::new(8, 3, 2, 0, HashFunction::Sha256, 0, 6);
+}
+
+#[test]
+fn test_try_new_options() {
+    assert_eq!(
+        ProofOptions::try_new(8, 3, 2, 0, HashFunction::Sha256, 0, 0),
+        Ok(ProofOptions::default())
+    );
+    assert_eq!(
+        ProofOptions::try_new(8, 3, 3, 0, HashFunction::Sha256, 0, 0),
+        Err("Folding factor must be 2, 4, 8 or 16".to_string())
+    );
+    assert!(ProofOptions::try_new(8, 0, 2, 0, HashFunction::Sha256, 0, 0).is_err());
+    assert!(ProofOptions::try_new(8, 3, 2, 0, HashFunction::Sha256, 17, 0).is_err());
+    assert!(ProofOptions::try_new(8, 3, 2, 0, HashFunction::Sha256, 0, usize::MAX).is_err());
+}
+
+#[test]
+fn test_check_options_against_air() {
+    let air = FibonacciSq::default();
+    assert_eq!(ProofOptions::default().check(&air), Ok(()));
+    // The queries are distinct points of the eval domain of 8192 elements
+    let options = ProofOptions::new(8, 8192, 2, 0, HashFunction::Sha256, 0, 0);
+    assert_eq!(options.check(&air), Ok(()));
+    let options = ProofOptions::new(8, 8193, 2, 0, HashFunction::Sha256, 0, 0);
+    assert!(options.check(&air).is_err());
+    // The remainder has at most as many coefficients as CP
+    let options = ProofOptions::new(8, 3, 2, 0, HashFunction::Sha256, 0, 1023);
+    assert_eq!(options.check(&air), Ok(()));
+    let options = ProofOptions::new(2, 30, 2, 0, HashFunction::Sha256, 0, 2047);
+    assert!(options.check(&air).is_err());
+    // The eval domain of 1024 * blowup elements must fit in the subgroup of 2^30 elements
+    let options = ProofOptions::new(1 << 20, 3, 2, 0, HashFunction::Sha256, 0, 0);
+    assert_eq!(options.check(&air), Ok(()));
+    let options = ProofOptions::new(1 << 21, 3, 2, 0, HashFunction::Sha256, 0, 0);
+    assert_eq!(
+        options.check(&air),
+        Err("Eval domain size must not exceed 2^30, the largest subgroup of the field".to_string())
+    );
+}
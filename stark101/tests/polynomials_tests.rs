@@ -1,9 +1,11 @@
 use ark_ff::{AdditiveGroup, FftField, Field};
 use ark_poly::polynomial::univariate::*;
 use ark_poly::{DenseUVPolynomial, Polynomial};
-use ark_std::{rand::Rng, test_rng, UniformRand};
-use stark101::common::{finite_fields::MyField, polynomials::*};
-use std::collections::HashSet;
+use ark_std::{rand::Rng, test_rng};
+use stark101::common::{
+    finite_fields::{MyField, MyFieldExt},
+    polynomials::*,
+};
 
 #[test]
 fn test_rand_poly_degree() {
@@ -120,7 +122,7 @@ fn test_divide_with_q_r_rand_polys() {
 
 #[test]
 fn test_prod_polys() {
-    let g = MyField::GENERATOR.pow(&(vec![3221225472_u64 / 1024]));
+    let g = MyField::GENERATOR.pow([3221225472_u64 / 1024]);
 
     let mut coefficients = vec![MyField::from(-1)];
     coefficients.extend(vec![MyField::ZERO; 1023]);
@@ -133,7 +135,7 @@ fn test_prod_polys() {
         coeffs: vec![MyField::ONE],
     }; // start product with polynomial 1
     for i in 0..1024 {
-        let gi = g.pow(&[i]); // g^i, exponentiation with a scalar field
+        let gi = g.pow([i]); // g^i, exponentiation with a scalar field
         let term = DensePolynomial {
             coeffs: vec![-gi, MyField::ONE],
         }; // (X - g^i) as (1, -gi)
@@ -155,41 +157,76 @@ fn test_eval_poly() {
 }
 
 #[test]
-fn test_poly_interpolation() {
-    let x_vals = vec![
-        MyField::from(0u64),
-        MyField::from(1u64),
-        MyField::from(2u64),
-    ];
-    let y_vals = vec![
-        MyField::from(0u64), // f(0)
-        MyField::from(1u64), // f(1)
-        MyField::from(8u64), // f(2)
-    ];
-    let poly = interpolate_polynomial(&x_vals, &y_vals);
-    let expected_poly = DensePolynomial {
-        coeffs: vec![MyField::ZERO, MyField::from(-2), MyField::from(3)],
-    }; // -2x + 3x^2
-    assert_eq!(
-        poly, expected_poly,
-        "Interpolated poly: {:?} must be equal to expected_poly: {:?}",
-        poly.coeffs, expected_poly.coeffs
-    );
+fn test_coset() {
+    let w = MyField::GENERATOR;
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 64]);
+    let points = coset(w, 64);
+    assert_eq!(points.len(), 64);
+    assert_eq!(points[0], w);
+    assert_eq!(points[5], w * h.pow([5]));
+    assert_eq!(points[63] * h, w);
+    for i in [0, 5, 63] {
+        assert_eq!(coset_element(w, 64, i), points[i]);
+    }
 }
 
 #[test]
-fn test_rand_poly_interpolation() {
+fn test_evaluate_on_coset() {
     let rng = &mut test_rng();
-    for _ in 0..10 {
-        let degree = rng.gen_range(0..=100);
+    let w = MyField::GENERATOR;
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 64]);
+    for degree in [0, 1, 17, 63] {
         let poly = DensePolynomial::<MyField>::rand(degree, rng);
-        let mut x_vals: HashSet<MyField> = HashSet::new();
-        while x_vals.len() < degree + 1 {
-            x_vals.insert(MyField::rand(rng));
+        let evaluations = evaluate_on_coset(&poly, w, 64);
+        for (i, evaluation) in evaluations.iter().enumerate() {
+            assert_eq!(
+                *evaluation,
+                poly.evaluate(&(w * h.pow([i as u64]))),
+                "evaluation number {} is wrong",
+                i
+            );
         }
-        let x_vals: Vec<MyField> = x_vals.into_iter().collect();
-        let y_vals: Vec<MyField> = x_vals.iter().map(|x| poly.evaluate(x)).collect();
-        let interpolated_poly = interpolate_polynomial(&x_vals, &y_vals);
+    }
+}
+
+#[test]
+fn test_interpolate_on_subgroup() {
+    let rng = &mut test_rng();
+    let g = MyField::GENERATOR.pow([3221225472_u64 / 128]);
+    for len in [1, 2, 128] {
+        let poly = DensePolynomial::<MyField>::rand(len - 1, rng);
+        let evaluations: Vec<MyField> = (0..len)
+            .map(|i| {
+                poly.evaluate(&MyField::GENERATOR.pow([3221225472_u64 / len as u64 * i as u64]))
+            })
+            .collect();
+        let interpolated_poly = interpolate_on_subgroup(&evaluations);
+        assert_eq!(
+            interpolated_poly, poly,
+            "Interpolated poly: {:?} must be equal to poly: {:?}",
+            interpolated_poly.coeffs, poly.coeffs
+        );
+    }
+    // Fewer evaluations than the size of the subgroup, like the 1023 steps of FibonacciSq
+    for len in [65, 100, 127] {
+        let poly = DensePolynomial::<MyField>::rand(len - 1, rng);
+        let evaluations: Vec<MyField> = (0..len)
+            .map(|i| poly.evaluate(&g.pow([i as u64])))
+            .collect();
+        let interpolated_poly = interpolate_on_subgroup(&evaluations);
+        assert_eq!(
+            interpolated_poly, poly,
+            "Interpolated poly: {:?} must be equal to poly: {:?}",
+            interpolated_poly.coeffs, poly.coeffs
+        );
+    }
+    for len in [3_usize, 5, 1023] {
+        let g = MyField::GENERATOR.pow([3221225472_u64 / len.next_power_of_two() as u64]);
+        let poly = DensePolynomial::<MyField>::rand(len - 1, rng);
+        let evaluations: Vec<MyField> = (0..len)
+            .map(|i| poly.evaluate(&g.pow([i as u64])))
+            .collect();
+        let interpolated_poly = interpolate_on_subgroup(&evaluations);
         assert_eq!(
             interpolated_poly, poly,
             "Interpolated poly: {:?} must be equal to poly: {:?}",
@@ -198,6 +235,34 @@ fn test_rand_poly_interpolation() {
     }
 }
 
+#[test]
+fn test_interpolate_on_coset() {
+    let rng = &mut test_rng();
+    let w = MyField::GENERATOR;
+    for len in [32, 30] {
+        let poly = DensePolynomial::<MyField>::rand(len - 1, rng);
+        let evaluations = evaluate_on_coset(&poly, w, 32);
+        let interpolated_poly = interpolate_on_coset(w, &evaluations[..len]);
+        assert_eq!(
+            interpolated_poly, poly,
+            "Interpolated poly: {:?} must be equal to poly: {:?}",
+            interpolated_poly.coeffs, poly.coeffs
+        );
+    }
+}
+
+#[test]
+fn test_interpolate_extension_on_coset() {
+    let rng = &mut test_rng();
+    let w = MyField::GENERATOR;
+    let poly = DensePolynomial::<MyFieldExt>::rand(15, rng);
+    let h = MyField::GENERATOR.pow([3221225472_u64 / 16]);
+    let evaluations: Vec<MyFieldExt> = (0..16)
+        .map(|i| poly.evaluate(&MyFieldExt::from_base_prime_field(w * h.pow([i]))))
+        .collect();
+    assert_eq!(interpolate_extension_on_coset(w, &evaluations), poly);
+}
+
 #[test]
 fn test_pow_poly() {
     let f = DensePolynomial {
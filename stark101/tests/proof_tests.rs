@@ -0,0 +1,96 @@
+use ark_ff::{AdditiveGroup, Field};
+use stark101::air::fibonacci_sq::FibonacciSq;
+use stark101::common::{
+    finite_fields::{MyField, MyFieldExt},
+    options::ProofOptions,
+    proof::{
+        deserialize_proof, extension_field_elements_to_bytes, field_elements_to_bytes,
+        serialize_proof, MerkleProof, ProofFormat, StarkProof,
+    },
+};
+use stark101::prover;
+
+#[test]
+fn test_merkle_proof_to_bytes() {
+    let proof = MerkleProof(vec![[1u8; 32], [2u8; 32]]);
+    let bytes = proof.to_bytes();
+    assert_eq!(bytes.len(), 64, "length of proof is wrong");
+    assert_eq!(bytes[..32], [1u8; 32]);
+    assert_eq!(bytes[32..], [2u8; 32]);
+}
+
+#[test]
+fn test_field_elements_to_bytes() {
+    let bytes = field_elements_to_bytes(&[MyField::from(1), MyField::from(258)]);
+    assert_eq!(bytes, vec![1, 0, 0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_extension_field_elements_to_bytes() {
+    let element = MyFieldExt::new(MyField::from(1), MyField::from(258), MyField::from(3));
+    let bytes = extension_field_elements_to_bytes(&[element, MyFieldExt::ONE]);
+    assert_eq!(bytes.len(), 48);
+    assert_eq!(
+        bytes[..24],
+        field_elements_to_bytes(&[MyField::from(1), MyField::from(258), MyField::from(3)])
+    );
+    assert_eq!(bytes[24..32], [1, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_serialize_proof() {
+    let air = FibonacciSq::default();
+    let proof = prover::run(&air, &air.generate_trace(), &ProofOptions::default());
+    let json = serde_json::to_string(&proof).expect("Failed to serialize proof");
+    let deserialized_proof: StarkProof = serde_json::from_str(&json).expect("Failed to parse JSON");
+    assert_eq!(proof, deserialized_proof, "proof must survive a round trip");
+
+    // Field elements must be canonical, elements of the extension field being encoded by their 3 coefficients
+    let mut proof = proof;
+    proof.fri_remainder = vec![MyFieldExt::ZERO];
+    let json = serde_json::to_string(&proof).unwrap();
+    let zero = format!("\"{}\"", "0".repeat(48));
+    let deserialized_proof: StarkProof = serde_json::from_str(&json.replace(
+        &zero,
+        &format!("\"{}{}\"", "0".repeat(32), "000000c000000000"), // modulus - 1
+    ))
+    .expect("Failed to parse JSON");
+    assert_eq!(
+        deserialized_proof.fri_remainder[0],
+        MyFieldExt::new(MyField::ZERO, MyField::ZERO, -MyField::ONE)
+    );
+    assert!(
+        serde_json::from_str::<StarkProof>(&json.replace(
+            &zero,
+            &format!("\"{}{}\"", "0".repeat(32), "010000c000000000") // modulus
+        ))
+        .is_err(),
+        "non canonical field elements must be rejected"
+    );
+    assert!(
+        serde_json::from_str::<StarkProof>(&json.replace(&zero, "\"0000000000000000\"")).is_err(),
+        "extension field elements must have 3 coefficients"
+    );
+}
+
+#[test]
+fn test_proof_formats() {
+    assert_eq!("json".parse(), Ok(ProofFormat::Json));
+    assert_eq!("binary".parse(), Ok(ProofFormat::Binary));
+    assert!("xml".parse::<ProofFormat>().is_err());
+
+    let air = FibonacciSq::default();
+    let proof = prover::run(&air, &air.generate_trace(), &ProofOptions::default());
+    let json = serialize_proof(&proof, ProofFormat::Json);
+    let binary = serialize_proof(&proof, ProofFormat::Binary);
+    assert!(binary.len() < json.len(), "binary proof must be smaller");
+    assert_eq!(
+        deserialize_proof(&json, ProofFormat::Json),
+        Ok(proof.clone())
+    );
+    assert_eq!(deserialize_proof(&binary, ProofFormat::Binary), Ok(proof));
+    assert!(
+        deserialize_proof(&binary[..100], ProofFormat::Binary).is_err(),
+        "truncated proof must be rejected"
+    );
+}
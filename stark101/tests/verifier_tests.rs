@@ -0,0 +1,632 @@
+use ark_ff::{AdditiveGroup, FftField, Field};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_std::test_rng;
+use stark101::air::{
+    fibonacci_sq::{FibonacciSq, FibonacciSqPublicInputs},
+    Air, BoundaryConstraint,
+};
+use stark101::common::{
+    channel::Channel,
+    finite_fields::{MyField, MyFieldExt},
+    fri::{create_fri_merkle_tree, generate_fri_commitments},
+    hasher::Sha256,
+    merkle::{create_merkle_tree_from_columns, get_merkle_cap},
+    options::{HashFunction, ProofOptions},
+    polynomials::{coset, evaluate_on_coset, interpolate_on_subgroup},
+    proof::{field_elements_to_bytes, MerkleCap, StarkProof},
+};
+use stark101::prover::{part1, part2, part4};
+use stark101::verifier::VerifierError;
+use stark101::{prover, verifier};
+use std::sync::OnceLock;
+
+// a[0] = 2 and a[i + 1] = a[i]^3 + 5 over 64 steps, the degree bound of CP exceeding the trace domain
+struct CubicSequence;
+
+impl CubicSequence {
+    fn generate_trace(&self) -> Vec<Vec<MyField>> {
+        let mut a = vec![MyField::from(2)];
+        for i in 1..self.trace_length() {
+            a.push(a[i - 1] * a[i - 1] * a[i - 1] + MyField::from(5));
+        }
+        vec![a]
+    }
+}
+
+impl Air for CubicSequence {
+    fn trace_width(&self) -> usize {
+        1
+    }
+
+    fn trace_length(&self) -> usize {
+        64
+    }
+
+    fn public_inputs(&self) -> Vec<MyField> {
+        vec![MyField::from(2), self.generate_trace()[0][63]]
+    }
+
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint> {
+        let public_inputs = self.public_inputs();
+        vec![
+            BoundaryConstraint {
+                column: 0,
+                step: 0,
+                value: public_inputs[0],
+            },
+            BoundaryConstraint {
+                column: 0,
+                step: 63,
+                value: public_inputs[1],
+            },
+        ]
+    }
+
+    fn transition_frame_size(&self) -> usize {
+        2
+    }
+
+    fn transition_constraint_degrees(&self) -> Vec<usize> {
+        vec![3]
+    }
+
+    fn evaluate_transition(&self, frame: &[Vec<MyField>]) -> Vec<MyField> {
+        let (a0, a1) = (frame[0][0], frame[1][0]);
+        vec![a1 - a0 * a0 * a0 - MyField::from(5)]
+    }
+}
+
+// Fibonacci sequence over two columns: (a, b) -> (b, a + b) starting from (1, 1), the claimed output being b[31]
+struct FibonacciPairs;
+
+impl FibonacciPairs {
+    fn generate_trace(&self) -> Vec<Vec<MyField>> {
+        let mut trace = vec![vec![MyField::from(1)], vec![MyField::from(1)]];
+        for i in 1..self.trace_length() {
+            let (a, b) = (trace[0][i - 1], trace[1][i - 1]);
+            trace[0].push(b);
+            trace[1].push(a + b);
+        }
+        trace
+    }
+}
+
+impl Air for FibonacciPairs {
+    fn trace_width(&self) -> usize {
+        2
+    }
+
+    fn trace_length(&self) -> usize {
+        32
+    }
+
+    fn public_inputs(&self) -> Vec<MyField> {
+        vec![MyField::from(1), MyField::from(3524578)]
+    }
+
+    fn boundary_constraints(&self) -> Vec<BoundaryConstraint> {
+        let public_inputs = self.public_inputs();
+        vec![
+            BoundaryConstraint {
+                column: 0,
+                step: 0,
+                value: public_inputs[0],
+            },
+            BoundaryConstraint {
+                column: 1,
+                step: 0,
+                value: public_inputs[0],
+            },
+            BoundaryConstraint {
+                column: 1,
+                step: 31,
+                value: public_inputs[1],
+            },
+        ]
+    }
+
+    fn transition_frame_size(&self) -> usize {
+        2
+    }
+
+    fn transition_constraint_degrees(&self) -> Vec<usize> {
+        vec![1, 1]
+    }
+
+    fn evaluate_transition(&self, frame: &[Vec<MyField>]) -> Vec<MyField> {
+        let (current, next) = (&frame[0], &frame[1]);
+        vec![next[0] - current[1], next[1] - current[0] - current[1]]
+    }
+}
+
+// Generating a proof is expensive so it is shared by all tests
+fn proof() -> StarkProof {
+    static PROOF: OnceLock<StarkProof> = OnceLock::new();
+    PROOF
+        .get_or_init(|| {
+            let air = FibonacciSq::default();
+            prover::run(&air, &air.generate_trace(), &ProofOptions::default())
+        })
+        .clone()
+}
+
+#[test]
+fn test_verify_proof() {
+    assert!(
+        verifier::verify(&FibonacciSq::default(), &proof(), &ProofOptions::default()).is_ok(),
+        "honest proof must be accepted"
+    );
+}
+
+// Changing a commitment changes all the challenges derived after it, so that the query indices no longer match the
+// ones derived by the verifier
+#[test]
+fn test_reject_forged_trace_root() {
+    let mut proof = proof();
+    proof.trace_cap.0[0] = [1u8; 32];
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_reject_forged_fri_root() {
+    let mut proof = proof();
+    proof.fri_caps[0].0[0] = [1u8; 32];
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_reject_forged_query() {
+    let mut proof = proof();
+    proof.queries[0].id = 0;
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_reject_repeated_query() {
+    let mut proof = proof();
+    proof.queries[1] = proof.queries[0].clone();
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_reject_missing_query() {
+    let mut proof = proof();
+    proof.queries.pop();
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::MalformedProof(
+            "Proof must contain 3 queries".to_string()
+        ))
+    );
+}
+
+// Tampering with the openings of the last query does not change the challenges
+#[test]
+fn test_reject_forged_trace_row() {
+    let mut proof = proof();
+    proof.queries[2].trace_frame[1].row[0] += MyField::from(1);
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::TraceMerkleProofMismatch)
+    );
+}
+
+#[test]
+fn test_reject_forged_fri_element() {
+    let mut proof = proof();
+    proof.queries[2].fri_layers[3].values[1] += MyFieldExt::ONE;
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::MerkleProofMismatch { layer: 3 })
+    );
+}
+
+#[test]
+fn test_reject_forged_merkle_proof() {
+    let mut proof = proof();
+    proof.trace_proof.0.pop();
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::TraceMerkleProofMismatch)
+    );
+    let mut proof = self::proof();
+    proof.fri_proofs[0].0.push([0u8; 32]);
+    assert_eq!(
+        verifier::verify(&FibonacciSq::default(), &proof, &ProofOptions::default()),
+        Err(VerifierError::MerkleProofMismatch { layer: 0 })
+    );
+}
+
+#[test]
+fn test_reject_cp_unrelated_to_trace() {
+    let air = FibonacciSq::default();
+    let options = ProofOptions::default();
+    let (eval_domain, trace_eval, trace_merkle, mut channel) =
+        part1::run(&air, &air.generate_trace(), &options);
+    channel.receive_random_extension_field_elements(air.num_constraints());
+    // A malicious prover commits to a low-degree polynomial unrelated to the trace instead of the CP
+    let CP = DensePolynomial::<MyFieldExt>::rand(1023, &mut test_rng());
+    let CP_eval: Vec<MyFieldExt> = eval_domain
+        .iter()
+        .map(|x| CP.evaluate(&MyFieldExt::from_base_prime_field(*x)))
+        .collect();
+    let CP_merkle = create_fri_merkle_tree::<Sha256>(&CP_eval, 2);
+    let CP_cap = MerkleCap(get_merkle_cap(&CP_merkle, options.cap_height));
+    channel.send(&CP_cap.to_bytes());
+    let (fri_layers, fri_merkles, fri_remainder) = generate_fri_commitments(
+        &eval_domain,
+        &CP_eval,
+        CP_merkle,
+        &options.fri_folding_factors(&air),
+        options.max_remainder_degree,
+        options.cap_height,
+        &mut channel,
+    );
+    let (pow_nonce, queries, trace_proof, fri_proofs) = part4::run(
+        &air,
+        trace_eval,
+        trace_merkle.clone(),
+        fri_layers.clone(),
+        fri_merkles.clone(),
+        &mut channel,
+        &options,
+    );
+    let proof = StarkProof {
+        options,
+        trace_cap: MerkleCap(get_merkle_cap(&trace_merkle, options.cap_height)),
+        CP_cap,
+        fri_caps: fri_merkles[1..]
+            .iter()
+            .map(|merkle| MerkleCap(get_merkle_cap(merkle, options.cap_height)))
+            .collect(),
+        fri_remainder,
+        pow_nonce,
+        queries,
+        trace_proof,
+        fri_proofs,
+    };
+    assert!(matches!(
+        verifier::verify(&air, &proof, &options),
+        Err(VerifierError::ConstraintMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_prove_and_verify_other_air() {
+    let air = CubicSequence;
+    assert_eq!(air.composition_degree_bound(), 128);
+    let proof = prover::run(&air, &air.generate_trace(), &ProofOptions::default());
+    assert!(
+        verifier::verify(&air, &proof, &ProofOptions::default()).is_ok(),
+        "honest proof of CubicSequence must be accepted"
+    );
+}
+
+#[test]
+fn test_prove_and_verify_multi_column_air() {
+    let air = FibonacciPairs;
+    let trace = air.generate_trace();
+    assert!(air.is_valid_trace(&trace), "trace must be valid");
+    let proof = prover::run(&air, &trace, &ProofOptions::default());
+    assert_eq!(
+        proof.queries[0].trace_frame[0].row.len(),
+        2,
+        "decommitted trace rows must contain both columns"
+    );
+    assert!(
+        verifier::verify(&air, &proof, &ProofOptions::default()).is_ok(),
+        "honest proof of FibonacciPairs must be accepted"
+    );
+}
+
+#[test]
+fn test_prove_and_verify_with_options() {
+    let air = CubicSequence;
+    let options = ProofOptions::new(4, 5, 2, 0, HashFunction::Sha256, 0, 0);
+    let proof = prover::run(&air, &air.generate_trace(), &options);
+    assert!(
+        verifier::verify(&air, &proof, &options).is_ok(),
+        "honest proof generated with other options must be accepted"
+    );
+}
+
+#[test]
+fn test_prove_and_verify_with_folding_factors() {
+    let air = CubicSequence;
+    for folding_factor in [4, 8, 16] {
+        let options = ProofOptions::new(4, 3, folding_factor, 0, HashFunction::Sha256, 1, 0);
+        let proof = prover::run(&air, &air.generate_trace(), &options);
+        assert!(
+            verifier::verify(&air, &proof, &options).is_ok(),
+            "honest proof folding by {} must be accepted",
+            folding_factor
+        );
+        // The queries open the whole coset of each layer
+        let last_opening = proof.queries[0].fri_layers.last().unwrap();
+        assert_eq!(
+            last_opening.values.len(),
+            *options.fri_folding_factors(&air).last().unwrap()
+        );
+        let mut forged_proof = proof.clone();
+        forged_proof.queries[1].fri_layers[0].values.pop();
+        assert_eq!(
+            verifier::verify(&air, &forged_proof, &options),
+            Err(VerifierError::MalformedProof(format!(
+                "Openings of FRI layer 0 must contain {} elements",
+                folding_factor
+            )))
+        );
+    }
+}
+
+#[test]
+fn test_prove_and_verify_with_remainder() {
+    let air = CubicSequence;
+    for max_remainder_degree in [7, 127] {
+        let options = ProofOptions::new(4, 3, 2, 0, HashFunction::Sha256, 0, max_remainder_degree);
+        let proof = prover::run(&air, &air.generate_trace(), &options);
+        assert!(
+            verifier::verify(&air, &proof, &options).is_ok(),
+            "honest proof with a remainder of degree {} must be accepted",
+            max_remainder_degree
+        );
+        assert_eq!(proof.fri_remainder.len(), max_remainder_degree + 1);
+        assert_eq!(
+            proof.fri_caps.len(),
+            options.num_fri_layers(&air).saturating_sub(1)
+        );
+        let mut forged_proof = proof.clone();
+        forged_proof.fri_remainder[0] += MyFieldExt::ONE;
+        assert_eq!(
+            verifier::verify(&air, &forged_proof, &options),
+            Err(VerifierError::ChallengeMismatch)
+        );
+        let mut forged_proof = proof;
+        forged_proof.fri_remainder.push(MyFieldExt::ZERO);
+        assert_eq!(
+            verifier::verify(&air, &forged_proof, &options),
+            Err(VerifierError::MalformedProof(format!(
+                "Remainder must have {} coefficients",
+                max_remainder_degree + 1
+            )))
+        );
+    }
+}
+
+#[test]
+fn test_reject_remainder_of_too_high_degree() {
+    // A remainder as large as the eval domain makes FRI fold nothing, so that its coefficients above the degree bound of
+    // CP are the only evidence of a false statement
+    let air = FibonacciSq::new(FibonacciSqPublicInputs {
+        claimed_output: FibonacciSqPublicInputs::default().claimed_output + MyField::ONE,
+        ..Default::default()
+    });
+    let options = ProofOptions::new(2, 30, 2, 0, HashFunction::Sha256, 0, 2047);
+    assert_eq!(options.eval_domain_size(&air), 2048);
+    assert_eq!(options.num_fri_layers(&air), 0);
+    assert!(options.check(&air).is_err());
+    // The trace of the true statement is committed as in part 1, which would reject it
+    let trace = air.generate_trace();
+    assert!(!air.is_valid_trace(&trace));
+    let eval_domain = coset(MyField::GENERATOR, 2048);
+    let trace_eval: Vec<Vec<MyField>> = trace
+        .iter()
+        .map(|column| evaluate_on_coset(&interpolate_on_subgroup(column), MyField::GENERATOR, 2048))
+        .collect();
+    let trace_merkle = create_merkle_tree_from_columns::<Sha256>(&trace_eval);
+    let mut channel = Channel::<Sha256>::new();
+    channel.send(&options.to_bytes());
+    channel.send(&field_elements_to_bytes(&air.public_inputs()));
+    channel.send(&MerkleCap(get_merkle_cap(&trace_merkle, options.cap_height)).to_bytes());
+    let (CP_eval, CP_merkle) = part2::run(&air, &eval_domain, &trace_eval, &mut channel, &options);
+    let CP_cap = MerkleCap(get_merkle_cap(&CP_merkle, options.cap_height));
+    let (fri_layers, fri_merkles, fri_remainder) = generate_fri_commitments(
+        &eval_domain,
+        &CP_eval,
+        CP_merkle,
+        &[],
+        options.max_remainder_degree,
+        options.cap_height,
+        &mut channel,
+    );
+    assert!(fri_remainder[air.composition_degree_bound()..]
+        .iter()
+        .any(|coeff| *coeff != MyFieldExt::ZERO));
+    let (pow_nonce, queries, trace_proof, fri_proofs) = part4::run(
+        &air,
+        trace_eval,
+        trace_merkle.clone(),
+        fri_layers,
+        fri_merkles,
+        &mut channel,
+        &options,
+    );
+    let proof = StarkProof {
+        options,
+        trace_cap: MerkleCap(get_merkle_cap(&trace_merkle, options.cap_height)),
+        CP_cap,
+        fri_caps: vec![],
+        fri_remainder,
+        pow_nonce,
+        queries,
+        trace_proof,
+        fri_proofs,
+    };
+    // Such options are rejected before the proof is read, as the remainder has more coefficients than CP
+    assert_eq!(
+        verifier::verify(&air, &proof, &options),
+        Err(VerifierError::InvalidOptions(
+            options.check(&air).unwrap_err()
+        ))
+    );
+}
+
+#[test]
+fn test_prove_and_verify_with_grinding() {
+    let air = CubicSequence;
+    let options = ProofOptions::new(4, 3, 2, 12, HashFunction::Sha256, 0, 0);
+    let proof = prover::run(&air, &air.generate_trace(), &options);
+    assert!(
+        verifier::verify(&air, &proof, &options).is_ok(),
+        "honest proof with grinding must be accepted"
+    );
+    // The prover finds the smallest nonce, so any smaller one fails
+    assert!(proof.pow_nonce > 0);
+    let mut forged_proof = proof.clone();
+    forged_proof.pow_nonce -= 1;
+    assert_eq!(
+        verifier::verify(&air, &forged_proof, &options),
+        Err(VerifierError::ProofOfWorkMismatch)
+    );
+}
+
+#[test]
+fn test_prove_and_verify_with_merkle_caps() {
+    let air = CubicSequence;
+    let options = ProofOptions::new(4, 3, 2, 0, HashFunction::Sha256, 3, 0);
+    let proof = prover::run(&air, &air.generate_trace(), &options);
+    assert_eq!(proof.trace_cap.0.len(), 8);
+    // The last committed FRI layer has 8 elements, hence a tree of 4 leaves
+    let (last_cap, fri_caps) = proof.fri_caps.split_last().unwrap();
+    assert!(fri_caps.iter().all(|cap| cap.0.len() == 8));
+    assert_eq!(last_cap.0.len(), 4);
+    assert!(
+        verifier::verify(&air, &proof, &options).is_ok(),
+        "honest proof committed with Merkle caps must be accepted"
+    );
+
+    let mut forged_proof = proof.clone();
+    forged_proof.trace_cap.0[3] = [1u8; 32];
+    assert_eq!(
+        verifier::verify(&air, &forged_proof, &options),
+        Err(VerifierError::ChallengeMismatch)
+    );
+    let mut forged_proof = proof;
+    forged_proof.CP_cap.0.pop();
+    assert_eq!(
+        verifier::verify(&air, &forged_proof, &options),
+        Err(VerifierError::MalformedProof(
+            "Cap of FRI layer 0 must contain 8 nodes".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_prove_and_verify_with_each_hash_function() {
+    let air = CubicSequence;
+    for hash in [
+        HashFunction::Sha256,
+        HashFunction::Blake3,
+        HashFunction::Keccak256,
+        HashFunction::Poseidon,
+    ] {
+        let options = ProofOptions::new(4, 3, 2, 0, hash, 0, 0);
+        let proof = prover::run(&air, &air.generate_trace(), &options);
+        assert!(
+            verifier::verify(&air, &proof, &options).is_ok(),
+            "honest proof with {:?} must be accepted",
+            hash
+        );
+        // The hash function is committed in the options
+        let other_hash = match hash {
+            HashFunction::Sha256 => HashFunction::Blake3,
+            _ => HashFunction::Sha256,
+        };
+        assert_eq!(
+            verifier::verify(
+                &air,
+                &proof,
+                &ProofOptions::new(4, 3, 2, 0, other_hash, 0, 0)
+            ),
+            Err(VerifierError::OptionsMismatch)
+        );
+    }
+}
+
+#[test]
+fn test_reject_proof_with_other_options() {
+    let air = CubicSequence;
+    let options = ProofOptions::new(4, 3, 2, 0, HashFunction::Sha256, 0, 0);
+    let proof = prover::run(&air, &air.generate_trace(), &options);
+    // Same number of queries and FRI layers, so that only the committed options differ
+    assert_eq!(
+        verifier::verify(&air, &proof, &ProofOptions::default()),
+        Err(VerifierError::OptionsMismatch)
+    );
+}
+
+#[test]
+fn test_reject_invalid_options() {
+    // The eval domain would exceed the largest subgroup of the field
+    let air = FibonacciSq::default();
+    assert_eq!(
+        verifier::verify(
+            &air,
+            &proof(),
+            &ProofOptions::new(1 << 21, 3, 2, 0, HashFunction::Sha256, 0, 0)
+        ),
+        Err(VerifierError::InvalidOptions(
+            "Eval domain size must not exceed 2^30, the largest subgroup of the field".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_prove_and_verify_other_instance() {
+    let mut public_inputs = FibonacciSqPublicInputs {
+        first_element: MyField::from(5),
+        second_element: MyField::from(7),
+        claimed_output: MyField::from(0),
+        num_steps: 100,
+    };
+    public_inputs.claimed_output = FibonacciSq::new(public_inputs).generate_trace()[0][99];
+    let air = FibonacciSq::new(public_inputs);
+    let proof = prover::run(&air, &air.generate_trace(), &ProofOptions::default());
+    assert!(
+        verifier::verify(&air, &proof, &ProofOptions::default()).is_ok(),
+        "honest proof of another instance must be accepted"
+    );
+    // The public inputs are absorbed into the channel, so that the proof does not hold for another claimed output
+    let wrong_air = FibonacciSq::new(FibonacciSqPublicInputs {
+        claimed_output: public_inputs.claimed_output + MyField::from(1),
+        ..public_inputs
+    });
+    assert_eq!(
+        verifier::verify(&wrong_air, &proof, &ProofOptions::default()),
+        Err(VerifierError::ChallengeMismatch)
+    );
+}
+
+#[test]
+fn test_prove_and_verify_small_instances() {
+    // The trace domain of 3 steps has 4 elements, the one of 8 steps is filled by the trace
+    for num_steps in [3, 8] {
+        let mut public_inputs = FibonacciSqPublicInputs {
+            first_element: MyField::from(1),
+            second_element: MyField::from(3141592),
+            claimed_output: MyField::from(0),
+            num_steps,
+        };
+        public_inputs.claimed_output =
+            FibonacciSq::new(public_inputs).generate_trace()[0][num_steps - 1];
+        let air = FibonacciSq::new(public_inputs);
+        let proof = prover::run(&air, &air.generate_trace(), &ProofOptions::default());
+        assert!(
+            verifier::verify(&air, &proof, &ProofOptions::default()).is_ok(),
+            "honest proof of {} steps must be accepted",
+            num_steps
+        );
+    }
+}